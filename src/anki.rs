@@ -0,0 +1,162 @@
+use crate::structure::*;
+use crate::japanese::*;
+use crate::report::*;
+use crate::config::*;
+use crate::html::escape;
+use crate::transcribe::replace;
+
+use std::fmt::Write;
+use std::collections::HashSet;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Cards { #[default] Text, Vocab }
+
+/// Flashcards with a front, a back and tags, in the order they were made.
+#[derive(Debug, Clone, Default)]
+pub struct Deck{
+    cards: Vec<[String; 3]>,
+    seen: HashSet<[String; 2]>,
+}
+
+/// Writes the deck as a tab separated file that Anki can import.
+pub fn write_deck(deck: &Deck, doc: &mut String){
+    let _ = writeln!(doc, "#separator:tab");
+    let _ = writeln!(doc, "#html:true");
+    let _ = writeln!(doc, "#tags column:3");
+    for [front, back, tags] in &deck.cards{
+        let _ = writeln!(doc, "{}\t{}\t{}", front, back, tags);
+    }
+}
+
+pub fn accumulate_deck(
+    chapter: Chapter, conf: &Config, cards: Cards, deck: &mut Deck, log: &mut String
+){
+    let mut location = Location::new(&chapter);
+    for picture in &chapter.pic{
+        location.advance(picture);
+        let texts = if let Some(texts) = &picture.text{ texts } else { continue; };
+        for text in texts{
+            log_todo(text, log);
+            let mut tags = vec![
+                tag("manga", &chapter.manga),
+                tag("volume", &location.volume.to_string()),
+                tag("chapter", &location.chapter.to_string()),
+                tag("page", &location.page.to_string()),
+            ];
+            for speaker in text.from.clone().vectorize(){
+                tags.push(tag("speaker", &conf.characters.canonical(&speaker)));
+            }
+            let tags = tags.join(" ");
+            let lines = text.lines.clone().vectorize();
+            let kmap = text.kmap.clone().vectorize();
+            let reps = &conf.transcription;
+            match cards{
+                Cards::Text => {
+                    let replacements = map_kanjis(&lines, &kmap);
+                    let mut back = Vec::new();
+                    if !kmap.is_empty(){
+                        back.push(field(&strip_escapes(&replacements), &reps.kanji_replacements));
+                    }
+                    if !could_contain_kanji(&replacements){
+                        let romanizeds = replacements.iter()
+                            .map(|rep| romanize_with(rep, &conf.romanization))
+                            .collect::<Vec<_>>();
+                        back.push(field(&romanizeds, &reps.romanized_replacements));
+                    }
+                    back.push(field(&text.transl.clone().vectorize(), &[]));
+                    back.push(field(&text.notes.clone().vectorize(), &[]));
+                    back.retain(|section| !section.is_empty());
+                    deck.cards.push([
+                        field(&strip_escapes(&lines), &reps.original_replacements),
+                        back.join("<br/><br/>"),
+                        tags,
+                    ]);
+                },
+                Cards::Vocab => {
+                    let matches = match_kanjis(&lines, &kmap);
+                    for (m, [kanji, reading]) in matches.iter().zip(&kmap){
+                        let m = if let Some(m) = m{ m } else { continue; };
+                        if !deck.seen.insert([kanji.clone(), reading.clone()]) { continue; }
+                        let mut back = vec![escape(reading)];
+                        if !could_contain_kanji(std::slice::from_ref(reading)){
+                            back.push(escape(&romanize_with(reading, &conf.romanization)));
+                        }
+                        back.push(field(
+                            &strip_escapes(&lines[m.line..=m.line]), &reps.original_replacements
+                        ));
+                        deck.cards.push([escape(kanji), back.join("<br/>"), tags.clone()]);
+                    }
+                },
+            }
+        }
+    }
+}
+
+fn field(lines: &[String], reps: &[[String; 2]]) -> String{
+    lines.iter()
+        .map(|line| escape(&replace(line, reps).replace(['\t', '\n'], " ")))
+        .collect::<Vec<_>>().join("<br/>")
+}
+
+// Anki tags can't contain spaces, "::" makes them hierarchical.
+fn tag(name: &str, value: &str) -> String{
+    format!("{}::{}", name, value.split_whitespace().collect::<Vec<_>>().join("_"))
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn deck(cards: Cards) -> Vec<[String; 3]>{
+        let chapter = toml::from_str::<Chapter>(r#"
+            manga = "Nichijou manga"
+            author = "a"
+            title = "t"
+            volume = 1
+            chapter = 2
+            [[pic]]
+            page = 3
+            [[pic.text]]
+            from = "nano"
+            # the tab is turned into a space
+            lines = ["朝 は\tね", "<と>"]
+            kmap = ["朝", "あさ"]
+            transl = "In the morning"
+            [[pic.text]]
+            from = ["hakase", "nano"]
+            lines = "朝"
+            kmap = ["朝", "あさ"]
+        "#).unwrap();
+        let mut deck = Deck::default();
+        accumulate_deck(chapter, &Config::default(), cards, &mut deck, &mut String::new());
+        deck.cards
+    }
+
+    #[test]
+    fn test_text_cards(){
+        let tags = "manga::Nichijou_manga volume::1 chapter::2 page::3";
+        assert_eq!(deck(Cards::Text), vec![
+            [
+                "朝は ね<br/>&lt;と&gt;".to_string(),
+                "あさは ね<br/>&lt;と&gt;<br/><br/>asa ha ne<br/>&lt;to&gt;<br/><br/>In the morning"
+                    .to_string(),
+                format!("{} speaker::nano", tags),
+            ],
+            [
+                "朝".to_string(),
+                "あさ<br/><br/>asa".to_string(),
+                format!("{} speaker::hakase speaker::nano", tags),
+            ],
+        ]);
+    }
+
+    #[test]
+    fn test_vocab_cards(){
+        // the second text has the same kmap entry, so it makes no card
+        assert_eq!(deck(Cards::Vocab), vec![[
+            "朝".to_string(),
+            "あさ<br/>asa<br/>朝は ね".to_string(),
+            "manga::Nichijou_manga volume::1 chapter::2 page::3 speaker::nano".to_string(),
+        ]]);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::structure::*;
+
+use serde::Deserialize;
+
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Character{
+    pub id: String,
+    pub name: Option<String>,
+    pub romanized: Option<String>,
+    pub aliases: Option<OneOrMore<String>>,
+    /// Not a character in the story, like the narrator or the audience.
+    #[serde(default)]
+    pub non_diegetic: bool,
+}
+
+/// The known characters of a series, looked up by their id or any of their aliases.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(from = "Vec<Character>")]
+pub struct Registry{
+    characters: Vec<Character>,
+    lookup: HashMap<String, usize>,
+}
+
+impl From<Vec<Character>> for Registry{
+    fn from(characters: Vec<Character>) -> Self{
+        let mut registry = Self::default();
+        registry.extend(characters);
+        registry
+    }
+}
+
+impl Registry{
+    /// Adds characters, ids and aliases that are already known keep their first declaration.
+    pub fn extend(&mut self, characters: Vec<Character>){
+        for character in characters{
+            if self.lookup.contains_key(&character.id) { continue; }
+            let index = self.characters.len();
+            self.lookup.insert(character.id.clone(), index);
+            for alias in character.aliases.clone().vectorize(){
+                self.lookup.entry(alias).or_insert(index);
+            }
+            self.characters.push(character);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.characters.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Character>{
+        self.lookup.get(name).map(|&i| &self.characters[i])
+    }
+
+    /// The id of the character with this id or alias, or the name itself if it is unknown.
+    pub fn canonical(&self, name: &str) -> String{
+        self.get(name).map(|c| c.id.clone()).unwrap_or_else(|| name.to_string())
+    }
+
+    /// How a character is shown in reports, for example `東雲なの (shinonome nano)`. The id is
+    /// shown instead of the romanized name when there is none.
+    pub fn display(&self, id: &str) -> String{
+        match self.get(id){
+            Some(Character{ name: Some(name), romanized: Some(romanized), .. }) =>
+                format!("{} ({})", name, romanized),
+            Some(Character{ name: Some(name), id, .. }) => format!("{} ({})", name, id),
+            Some(Character{ romanized: Some(romanized), .. }) => romanized.clone(),
+            _ => id.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_display(){
+        let reg = toml::from_str::<HashMap<String, Registry>>(r#"
+            [[character]]
+            id = "nano"
+            name = "東雲なの"
+            romanized = "shinonome nano"
+            aliases = "しののめ"
+            [[character]]
+            id = "hakase"
+            name = "はかせ"
+            [[character]]
+            id = "sakamoto"
+            romanized = "sakamoto-san"
+        "#).unwrap().remove("character").unwrap();
+        assert_eq!(reg.display("nano"), "東雲なの (shinonome nano)");
+        assert_eq!(reg.display(&reg.canonical("しののめ")), "東雲なの (shinonome nano)");
+        assert_eq!(reg.display("hakase"), "はかせ (hakase)");
+        assert_eq!(reg.display("sakamoto"), "sakamoto-san");
+        assert_eq!(reg.display("yukko"), "yukko");
+    }
+}
@@ -0,0 +1,173 @@
+use crate::characters::*;
+use crate::japanese::RomanizationScheme;
+use crate::normalize::Normalization;
+use crate::stats::Prominence;
+
+use serde::Deserialize;
+
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::collections::HashMap;
+
+pub const CONFIG_FILE: &str = "mangatrans.toml";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config{
+    /// Ids or aliases of characters that are not in the story, like the narrator or the audience.
+    pub non_diegetic: Vec<String>,
+    /// Also leave the non-diegetic characters out of the timeline and the interaction graph.
+    pub hide_non_diegetic: bool,
+    pub transcription: TranscriptionConfig,
+    pub romanization: Romanization,
+    pub normalization: Normalization,
+    pub prominence: Prominence,
+    #[serde(rename = "character")]
+    pub characters: Registry,
+    pub locations: HashMap<String, String>,
+    /// The meaning of sound effects, for the glossary.
+    pub sfx: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct TranscriptionConfig{
+    pub sections: Vec<Section>,
+    pub line_separator: String,
+    pub original_replacements: Vec<[String; 2]>,
+    pub kanji_replacements: Vec<[String; 2]>,
+    pub romanized_replacements: Vec<[String; 2]>,
+    pub title_header: usize,
+    pub page_header: usize,
+    pub bullet: String,
+    pub indent: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Romanization{
+    pub scheme: RomanizationScheme,
+    /// Romanize standalone は, へ and を as the particles wa, e and o.
+    pub particles: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Section{ Original, Kanji, Romanized, Translation, Notes }
+
+impl Section{
+    pub fn name(&self) -> &'static str{
+        match self{
+            Self::Original => "original",
+            Self::Kanji => "kanji",
+            Self::Romanized => "romanized",
+            Self::Translation => "translation",
+            Self::Notes => "notes",
+        }
+    }
+}
+
+impl Config{
+    /// Whether the character is not in the story, by the registry or by the config.
+    pub fn is_non_diegetic(&self, name: &str) -> bool{
+        let id = self.characters.canonical(name);
+        self.characters.get(&id).is_some_and(|character| character.non_diegetic)
+            || self.non_diegetic.iter().any(|other| self.characters.canonical(other) == id)
+    }
+
+    /// Whether the character is left out of the timeline and the interaction graph.
+    pub fn is_hidden(&self, name: &str) -> bool{
+        self.hide_non_diegetic && self.is_non_diegetic(name)
+    }
+}
+
+impl TranscriptionConfig{
+    /// The replacements done on the lines of a section.
+    pub fn replacements(&self, section: Section) -> &[[String; 2]]{
+        match section{
+            Section::Original => &self.original_replacements,
+            Section::Kanji => &self.kanji_replacements,
+            Section::Romanized => &self.romanized_replacements,
+            Section::Translation | Section::Notes => &[],
+        }
+    }
+}
+
+impl Default for TranscriptionConfig{
+    fn default() -> Self{
+        fn table(pairs: &[(&str, &str)]) -> Vec<[String; 2]>{
+            pairs.iter().map(|(a, b)| [a.to_string(), b.to_string()]).collect()
+        }
+        Self{
+            sections: vec![
+                Section::Original, Section::Kanji, Section::Romanized,
+                Section::Translation, Section::Notes
+            ],
+            line_separator: String::from(" <br/> "),
+            original_replacements: table(&[(" ", "")]),
+            kanji_replacements: table(&[(" ", "")]),
+            romanized_replacements: table(&[
+                ("　", " "), ("ー", "-"), ("〜", "~"), ("！", "!"), ("？", "?")
+            ]),
+            title_header: 1,
+            page_header: 5,
+            bullet: String::from("- "),
+            indent: String::from("  "),
+        }
+    }
+}
+
+/// Loads the config given with `--config`, or else the `mangatrans.toml` found next to the
+/// first input file or in the working directory, if there is one.
+pub fn get_config(explicit: Option<&PathBuf>, first_input: &Path) -> Result<Option<Config>, String>{
+    let file = if let Some(file) = explicit{
+        file.clone()
+    } else {
+        let beside = first_input.with_file_name(CONFIG_FILE);
+        let cwd = PathBuf::from(CONFIG_FILE);
+        if beside.is_file(){
+            beside
+        } else if cwd.is_file(){
+            cwd
+        } else {
+            return Ok(None);
+        }
+    };
+    let contents = fs::read_to_string(&file).map_err(|error|
+        format!("Could not read config file: \"{}\".\n\tError: {}", file.display(), error)
+    )?;
+    toml::from_str::<Config>(&contents).map(Some).map_err(|error|
+        format!("Could not parse config file: \"{}\".\n\tError: {}", file.display(), error)
+    )
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_non_diegetic(){
+        let conf = toml::from_str::<Config>(r#"
+            non_diegetic = ["crowd"]
+            [[character]]
+            id = "narator"
+            aliases = "naration"
+            non_diegetic = true
+            [[character]]
+            id = "audience"
+            aliases = "crowd"
+            [[character]]
+            id = "nano"
+        "#).unwrap();
+        assert!(conf.is_non_diegetic("narator"));
+        assert!(conf.is_non_diegetic("naration"));
+        assert!(conf.is_non_diegetic("audience"));
+        assert!(conf.is_non_diegetic("crowd"));
+        assert!(!conf.is_non_diegetic("nano"));
+        assert!(!conf.is_non_diegetic("yukko"));
+        assert!(!conf.is_hidden("narator"));
+        let conf = Config{ hide_non_diegetic: true, ..conf };
+        assert!(conf.is_hidden("naration"));
+        assert!(!conf.is_hidden("nano"));
+    }
+}
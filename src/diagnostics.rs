@@ -0,0 +1,62 @@
+use std::fmt::Write;
+use std::path::Path;
+
+/// Turns a toml/serde error into a message with the file, position, a snippet of the offending
+/// line with carets underneath and, for common mistakes, a hint on how to fix it.
+pub fn parse_diagnostic(file: &Path, contents: &str, error: &toml::de::Error) -> String{
+    let mut diag = String::new();
+    let message = error.message().trim();
+    let _ = writeln!(diag, "Error: could not parse \"{}\": {}", file.display(), message);
+    if let Some(span) = error.span(){
+        let start = span.start.min(contents.len());
+        let line_start = contents[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = contents[start..].find('\n').map(|i| start + i).unwrap_or(contents.len());
+        let line = &contents[line_start..line_end];
+        let line_nr = contents[..start].matches('\n').count() + 1;
+        let column = contents[line_start..start].chars().count() + 1;
+        let end = span.end.clamp(start, line_end);
+
+        let gutter = " ".repeat(line_nr.to_string().len());
+        let _ = writeln!(diag, "{}--> {}:{}:{}", gutter, file.display(), line_nr, column);
+        let _ = writeln!(diag, "{} |", gutter);
+        let _ = writeln!(diag, "{} | {}", line_nr, line);
+        let _ = writeln!(
+            diag, "{} | {}{}", gutter,
+            " ".repeat(width(&contents[line_start..start])),
+            "^".repeat(width(&contents[start..end]).max(1)),
+        );
+        if let Some(hint) = hint(message, line){
+            let _ = writeln!(diag, "{} = hint: {}", gutter, hint);
+        }
+    } else if let Some(hint) = hint(message, ""){
+        let _ = writeln!(diag, "\thint: {}", hint);
+    }
+    diag
+}
+
+fn hint(message: &str, line: &str) -> Option<&'static str>{
+    let key = line.split('=').next().unwrap_or("").trim();
+    if message.contains("missing field `lines`"){
+        Some("every [[pic.text]] needs `lines` with the transcribed text.")
+    } else if message.contains("missing field `pic`"){
+        Some("a chapter needs at least one [[pic]].")
+    } else if message.contains("missing field"){
+        Some("a chapter needs `volume`, `chapter` and `title`, and `manga` and `author` unless \
+            series.toml has them.")
+    } else if key == "kmap"{
+        Some("kmap entries are pairs of two strings: kmap = [[\"今日\", \"きょう\"], ...].")
+    } else if message.contains("untagged enum Either"){
+        Some("this field takes a string or an array of strings.")
+    } else if message.contains("duplicate key"){
+        Some("a field can only be set once per table, did you forget a [[pic]] or [[pic.text]]?")
+    } else if message.contains("invalid type") && message.contains("expected a string"){
+        Some("text has to be written between quotes.")
+    } else {
+        None
+    }
+}
+
+// Japanese characters take up two columns in a terminal.
+fn width(string: &str) -> usize{
+    string.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
@@ -0,0 +1,127 @@
+use crate::html::escape;
+
+use std::fmt::Write;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum NodeWeight { #[default] Appearances, Morae }
+
+#[derive(Debug, Clone, Default)]
+pub struct Graph{
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Node{
+    pub id: String,
+    pub label: String,
+    pub weight: usize,
+}
+
+/// Someone speaking to someone else, for some morae in total.
+#[derive(Debug, Clone)]
+pub struct Edge{
+    pub from: String,
+    pub to: String,
+    pub morae: usize,
+}
+
+/// Writes the graph in the DOT language of Graphviz. Graphviz only uses the weight of edges, so
+/// the weight of a node is shown in it's label.
+pub fn write_dot(graph: &Graph, doc: &mut String){
+    fn quote(string: &str) -> String{
+        format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+    let _ = writeln!(doc, "digraph characters {{");
+    for node in &graph.nodes{
+        let label = format!("{} ({})", node.label, node.weight);
+        let _ = writeln!(doc, "    {} [label={}];", quote(&node.id), quote(&label));
+    }
+    for edge in &graph.edges{
+        let _ = writeln!(
+            doc, "    {} -> {} [weight={}, label=\"{}\"];",
+            quote(&edge.from), quote(&edge.to), edge.morae, edge.morae
+        );
+    }
+    let _ = writeln!(doc, "}}");
+}
+
+/// Writes the graph as GraphML, with the label and weight of the nodes and the morae of the
+/// edges as data.
+pub fn write_graphml(graph: &Graph, doc: &mut String){
+    let _ = writeln!(doc, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    let _ = writeln!(doc, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">");
+    for (id, domain, kind) in [("label", "node", "string"), ("weight", "node", "int"),
+        ("morae", "edge", "int")]
+    {
+        let _ = writeln!(
+            doc, "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>",
+            id, domain, id, kind
+        );
+    }
+    let _ = writeln!(doc, "  <graph id=\"characters\" edgedefault=\"directed\">");
+    for node in &graph.nodes{
+        let _ = writeln!(doc, "    <node id=\"{}\">", escape(&node.id));
+        let _ = writeln!(doc, "      <data key=\"label\">{}</data>", escape(&node.label));
+        let _ = writeln!(doc, "      <data key=\"weight\">{}</data>", node.weight);
+        let _ = writeln!(doc, "    </node>");
+    }
+    for edge in &graph.edges{
+        let _ = writeln!(
+            doc, "    <edge source=\"{}\" target=\"{}\">", escape(&edge.from), escape(&edge.to)
+        );
+        let _ = writeln!(doc, "      <data key=\"morae\">{}</data>", edge.morae);
+        let _ = writeln!(doc, "    </edge>");
+    }
+    let _ = writeln!(doc, "  </graph>");
+    let _ = writeln!(doc, "</graphml>");
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn graph() -> Graph{
+        Graph{
+            nodes: vec![
+                Node{ id: "nano".to_string(), label: "東雲なの".to_string(), weight: 3 },
+                Node{
+                    id: "sakamoto".to_string(), label: "\"sakamoto\" <cat>".to_string(), weight: 1
+                },
+            ],
+            edges: vec![Edge{ from: "nano".to_string(), to: "sakamoto".to_string(), morae: 12 }],
+        }
+    }
+
+    #[test]
+    fn test_dot(){
+        let mut doc = String::new();
+        write_dot(&graph(), &mut doc);
+        assert_eq!(doc, concat!(
+            "digraph characters {\n",
+            "    \"nano\" [label=\"東雲なの (3)\"];\n",
+            "    \"sakamoto\" [label=\"\\\"sakamoto\\\" <cat> (1)\"];\n",
+            "    \"nano\" -> \"sakamoto\" [weight=12, label=\"12\"];\n",
+            "}\n",
+        ));
+    }
+
+    #[test]
+    fn test_graphml(){
+        let mut doc = String::new();
+        write_graphml(&graph(), &mut doc);
+        assert!(doc.contains(concat!(
+            "    <node id=\"sakamoto\">\n",
+            "      <data key=\"label\">&quot;sakamoto&quot; &lt;cat&gt;</data>\n",
+            "      <data key=\"weight\">1</data>\n",
+            "    </node>\n",
+        )));
+        assert!(doc.contains(concat!(
+            "    <edge source=\"nano\" target=\"sakamoto\">\n",
+            "      <data key=\"morae\">12</data>\n",
+            "    </edge>\n",
+        )));
+        assert!(doc.starts_with("<?xml"));
+        assert!(doc.ends_with("  </graph>\n</graphml>\n"));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::structure::*;
+use crate::japanese::*;
+use crate::config::*;
+use crate::transcribe::*;
+
+use std::fmt::Write;
+
+/// Writes the transcription as an html page. Instead of a separate kanji replaced line, the
+/// readings from the kmap are put above the kanji in the original lines as furigana.
+pub fn write_html_transcription(
+    chapter: Chapter, romanized: Option<&str>, conf: &Config, html: &mut String, log: &mut String
+){
+    let pictures = transcript(&chapter, conf, log);
+    let conf = &conf.transcription;
+    let title = escape(&chapter.title);
+    let _ = writeln!(html, "<!DOCTYPE html>");
+    let _ = writeln!(html, "<html lang=\"ja\">");
+    let _ = writeln!(html, "<head>");
+    let _ = writeln!(html, "<meta charset=\"utf-8\">");
+    let _ = writeln!(html, "<title>{}</title>", title);
+    let _ = writeln!(html, "<style>");
+    let _ = writeln!(html, "ruby rt {{ font-size: 0.6em; }}");
+    let _ = writeln!(html, ".original {{ font-size: 1.4em; line-height: 2.2em; }}");
+    let _ = writeln!(html, ".thought {{ font-style: italic; }}");
+    let _ = writeln!(html, ".narration {{ border-left: 2px solid gray; }}");
+    let _ = writeln!(html, ".sign {{ color: dimgray; }}");
+    let _ = writeln!(html, ".sfx {{ font-weight: bold; }}");
+    let _ = writeln!(html, "</style>");
+    let _ = writeln!(html, "</head>");
+    let _ = writeln!(html, "<body>");
+    let _ = writeln!(html, "{}", header(conf.title_header, &title));
+    let _ = write!(html, "<p>Manga: {}", escape(&chapter.manga));
+    if let Some(romanized) = romanized{
+        let _ = write!(html, " ({})", escape(romanized));
+    }
+    let _ = writeln!(html, "<br/>");
+    let _ = writeln!(html, "Author: {}<br/>", escape(&chapter.author));
+    let _ = writeln!(html, "Volume: {}<br/>", chapter.volume);
+    let _ = write!(html, "Chapter: {}", chapter.chapter);
+    if let Some(subchap) = chapter.subchapter{
+        let _ = write!(html, "<br/>\nSub Chapter: {}", subchap);
+    }
+    let _ = writeln!(html, "</p>");
+
+    for picture in pictures{
+        if let Some(page) = picture.page{
+            let _ = writeln!(html, "{}", header(conf.page_header, &format!("Page: {}", page)));
+        }
+        let _ = writeln!(html, "<ul>");
+        let _ = writeln!(html, "<li>picture {}", picture.nr);
+        if picture.labeled{
+            let _ = writeln!(html, "<ul>");
+        }
+        for (n, text) in picture.texts.iter().enumerate(){
+            if picture.labeled{
+                let _ = writeln!(html, "<li>{}", text.label(n + 1));
+            }
+            write_text(html, conf, text);
+            if picture.labeled{
+                let _ = writeln!(html, "</li>");
+            }
+        }
+        if picture.labeled{
+            let _ = writeln!(html, "</ul>");
+        }
+        let _ = writeln!(html, "</li>");
+        let _ = writeln!(html, "</ul>");
+    }
+    let _ = writeln!(html, "</body>");
+    let _ = writeln!(html, "</html>");
+}
+
+fn write_text(html: &mut String, conf: &TranscriptionConfig, text: &TextTranscript){
+    let _ = writeln!(html, "<ul class=\"{}\">", text.kind.name());
+    for (section, lines) in &text.sections{
+        let lines = match section{
+            Section::Original => {
+                let matches = match_kanjis(&text.lines, &text.kmap);
+                text.lines.iter().enumerate().map(|(i, line)|
+                    kanji_segments(line, i, &matches, &text.kmap).into_iter()
+                        .map(|(segment, reading)| {
+                            let segment = replace(segment, &conf.original_replacements);
+                            let segment = escape(&segment.replace(PARTICLE_ESCAPE, ""));
+                            match reading{
+                                Some(reading) => format!(
+                                    "<ruby>{}<rt>{}</rt></ruby>", segment,
+                                    escape(&replace(reading, &conf.kanji_replacements))
+                                ),
+                                None => segment,
+                            }
+                        })
+                        .collect::<String>()
+                ).collect::<Vec<_>>()
+            },
+            // the readings are already shown as furigana
+            Section::Kanji => continue,
+            _ => lines.iter()
+                .map(|line| escape(&replace(line, conf.replacements(*section))))
+                .collect::<Vec<_>>(),
+        };
+        let _ = writeln!(
+            html, "<li class=\"{}\">{}</li>", section.name(), lines.join(&conf.line_separator)
+        );
+    }
+    let _ = writeln!(html, "</ul>");
+}
+
+fn header(rank: usize, content: &str) -> String{
+    let rank = rank.clamp(1, 6);
+    format!("<h{}>{}</h{}>", rank, content, rank)
+}
+
+pub fn escape(string: &str) -> String{
+    string.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_html_transcription(){
+        let chapter = toml::from_str::<Chapter>(r#"
+            manga = "m"
+            author = "a"
+            title = "<t>"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            page = 1
+            [[pic.text]]
+            from = "a"
+            lines = "朝 は \"<&>\""
+            kmap = ["朝", "ちょう"]
+            transl = "Mornings & <you>"
+        "#).unwrap();
+        let mut html = String::new();
+        write_html_transcription(chapter, None, &Config::default(), &mut html, &mut String::new());
+        assert!(html.contains("<title>&lt;t&gt;</title>"));
+        assert!(html.contains(
+            "<li class=\"original\"><ruby>朝<rt>ちょう</rt></ruby>は&quot;&lt;&amp;&gt;&quot;</li>"
+        ));
+        assert!(html.contains("<li class=\"translation\">Mornings &amp; &lt;you&gt;</li>"));
+        // the readings are furigana instead of a kanji replaced line
+        assert!(!html.contains("class=\"kanji\""));
+    }
+}
@@ -1,3 +1,7 @@
+use crate::config::Romanization;
+
+use serde::Deserialize;
+
 pub fn split_hirakata(string: &str) -> Vec<String>{
     let mut res = Vec::new();
     let chars: Vec<char> = string.chars().collect();
@@ -11,7 +15,7 @@ pub fn split_hirakata(string: &str) -> Vec<String>{
         let comb = format!("{}{}", a, b);
         if let Hepburn::Roman(_) = Hepburn::from(&comb){
             res.push(comb);
-            i += 1;
+            i += 2;
             continue;
         }
         if is_punctuation(a) {
@@ -35,7 +39,71 @@ pub fn split_hirakata(string: &str) -> Vec<String>{
     res
 }
 
+/// Put before a standalone は, へ or を that is not a particle, like `\\は`.
+pub const PARTICLE_ESCAPE: char = '\\';
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RomanizationScheme{
+    /// Hepburn with long vowels written double: ittekiimaasu
+    #[default] Hepburn,
+    /// Hepburn with macrons for long vowels: ittekīmāsu
+    HepburnMacrons,
+    /// Kunrei-shiki: si, tu, zi
+    Kunrei,
+    /// Nihon-shiki: like Kunrei-shiki but with di, du and wo
+    Nihon,
+}
+
+/// Removes particle escapes, for when the lines are shown as they are.
+pub fn strip_escapes(strings: &[String]) -> Vec<String>{
+    strings.iter().map(|string| string.replace(PARTICLE_ESCAPE, "")).collect()
+}
+
+pub fn romanize_with(string: &str, options: &Romanization) -> String{
+    let scheme = options.scheme;
+    if !options.particles && !string.contains(PARTICLE_ESCAPE){
+        return romanize_scheme(string, scheme);
+    }
+    fn romanize_word(word: &str, options: &Romanization) -> String{
+        let scheme = options.scheme;
+        if let Some(escaped) = word.strip_prefix(PARTICLE_ESCAPE){
+            return romanize_scheme(escaped, scheme);
+        }
+        if options.particles{
+            let core = word.trim_end_matches(|c| c != 'ー' && is_punctuation(c));
+            let particle = match core{
+                "は" => Some("wa"), "へ" => Some("e"),
+                "を" if scheme == RomanizationScheme::Nihon => Some("wo"),
+                "を" => Some("o"),
+                _ => None,
+            };
+            if let Some(particle) = particle{
+                return format!("{}{}", particle, romanize_scheme(&word[core.len()..], scheme));
+            }
+        }
+        romanize_scheme(word, scheme)
+    }
+    let mut res = String::new();
+    let mut rest = string;
+    while !rest.is_empty(){
+        let word_end = rest.find(is_whitespace).unwrap_or(rest.len());
+        res.push_str(&romanize_word(&rest[..word_end], options));
+        rest = &rest[word_end..];
+        let space_end = rest.find(|c| !is_whitespace(c)).unwrap_or(rest.len());
+        res.push_str(&romanize_scheme(&rest[..space_end], scheme));
+        rest = &rest[space_end..];
+    }
+    res
+}
+
+/// Romanizes with the default scheme, Hepburn, without the particle rules of the config.
+#[cfg(test)]
 pub fn romanize(string: &str) -> String{
+    romanize_scheme(string, RomanizationScheme::Hepburn)
+}
+
+pub fn romanize_scheme(string: &str, scheme: RomanizationScheme) -> String{
     let mut res = String::new();
     if string.chars().next().is_none() { return res; }
     let chars: Vec<char> = string.chars().chain([' ']).collect();
@@ -43,41 +111,89 @@ pub fn romanize(string: &str) -> String{
     let mut i = 0;
     let mut prev = chars[0];
     let mut tsu = false;
+    let mut last = String::new();
+    let macrons = scheme == RomanizationScheme::HepburnMacrons;
 
-    fn push(res: &mut String, roman: &str, tsu: &mut bool, prev: &mut char, last: bool){
+    fn push(res: &mut String, roman: &str, tsu: &mut bool, prev: &mut char, macrons: bool){
         if *tsu{
             let next = roman.chars().next();
             if let Some(next) = next{
-                if last || roman == &*prev.to_string(){
+                // a small tsu before a vowel is a glottal stop, not a doubled consonant
+                if "aiueo".contains(next){
                     res.push('h');
                 } else {
-                    res.push_str(&next.to_string());
+                    res.push(next);
                 }
             }
             *tsu = false;
+        } else if macrons && matches!(
+            (res.chars().last(), roman), (Some('a'), "a") | (Some('o' | 'u'), "u")
+        ){
+            let vowel = res.pop().unwrap_or_default();
+            res.push(macron(vowel));
+            return;
         }
         res.push_str(roman);
         if let Some(last) = roman.chars().last(){ *prev = last; }
     }
 
+    fn voice(roman: &str, scheme: RomanizationScheme) -> String{
+        // Kunrei-shiki writes ぢ and づ as zi and zu, Nihon-shiki as di and du
+        let kunrei: &[(&str, &str)] = if scheme == RomanizationScheme::Kunrei{
+            &[("ti", "zi"), ("tu", "zu"), ("ty", "zy")]
+        } else {
+            &[]
+        };
+        for &(unvoiced, voiced) in kunrei.iter().chain(&[
+            ("sh", "j"), ("ch", "j"), ("ts", "z"), ("k", "g"), ("s", "z"), ("t", "d"), ("h", "b"),
+            ("f", "b")
+        ]){
+            if let Some(rest) = roman.strip_prefix(unvoiced){
+                return format!("{}{}", voiced, rest);
+            }
+        }
+        roman.to_string()
+    }
+
+    fn macron(vowel: char) -> char{
+        match vowel{
+            'a' => 'ā', 'i' => 'ī', 'u' => 'ū', 'e' => 'ē', 'o' => 'ō',
+            _ => vowel,
+        }
+    }
+
     while i < l{
         let a = chars[i];
         let b = chars[i + 1];
-        let last = i == l - 1;
         if a != 'ー' && (is_whitespace(a) || is_punctuation(a)){
             if tsu { res.push('h'); }
             tsu = false;
         }
         let comb = format!("{}{}", a, b);
-        if let Hepburn::Roman(roman) = Hepburn::from(&comb){
-            push(&mut res, &roman, &mut tsu, &mut prev, last);
+        if let Hepburn::Roman(roman) = Hepburn::from_scheme(&comb, scheme){
+            push(&mut res, &roman, &mut tsu, &mut prev, macrons);
+            last = roman;
             i += 2;
             continue;
         }
+        if "ゝゞヽヾ".contains(a){
+            // iteration marks repeat the kana before them, the ones with a dakuten voiced
+            let roman = if "ゞヾ".contains(a) { voice(&last, scheme) } else { last.clone() };
+            push(&mut res, &roman, &mut tsu, &mut prev, macrons);
+            i += 1;
+            continue;
+        }
         let a = a.to_string();
-        match Hepburn::from(&a){
-            Hepburn::Roman(roman) => push(&mut res, &roman, &mut tsu, &mut prev, last),
+        match Hepburn::from_scheme(&a, scheme){
+            Hepburn::Roman(roman) => {
+                push(&mut res, &roman, &mut tsu, &mut prev, macrons);
+                last = roman;
+            },
             Hepburn::SmallTsu => tsu = true,
+            Hepburn::Enlongate if macrons && res.ends_with(prev) && "aiueo".contains(prev) => {
+                res.pop();
+                res.push(macron(prev));
+            },
             Hepburn::Enlongate => res.push(prev),
             Hepburn::Fail => res.push_str(&a),
         }
@@ -90,6 +206,33 @@ pub fn romanize(string: &str) -> String{
 pub enum Hepburn{ Roman(String), SmallTsu, Enlongate, Fail }
 
 impl Hepburn{
+    /// Like `from` but in the given romanization scheme.
+    fn from_scheme(string: &str, scheme: RomanizationScheme) -> Self{
+        use RomanizationScheme::*;
+        let nihon = match string{
+            "ぢ" | "ヂ" => "di", "づ" | "ヅ" => "du", "を" | "ヲ" => "wo",
+            "ぢゃ" | "ヂャ" => "dya", "ぢゅ" | "ヂュ" => "dyu", "ぢょ" | "ヂョ" => "dyo",
+            _ => "",
+        };
+        if scheme == Nihon && !nihon.is_empty(){
+            return Self::Roman(nihon.to_string());
+        }
+        match (scheme, Self::from(string)){
+            (Kunrei | Nihon, Self::Roman(roman)) => {
+                let roman = match roman.as_str(){
+                    "shi" => "si", "sha" => "sya", "shu" => "syu", "sho" => "syo",
+                    "chi" => "ti", "cha" => "tya", "chu" => "tyu", "cho" => "tyo",
+                    "tsu" => "tu", "fu" => "hu",
+                    "ji" => "zi", "ja" => "zya", "ju" => "zyu", "jo" => "zyo",
+                    "she" => "sye", "che" => "tye", "je" => "zye",
+                    other => other,
+                };
+                Self::Roman(roman.to_string())
+            },
+            (_, hepburn) => hepburn,
+        }
+    }
+
     fn from(string: &str) -> Self{
         let temp = match string{
             "あ" => "a", "ア" => "a", "い" => "i", "イ" => "i", "う" => "u", "ウ" => "u",
@@ -109,7 +252,8 @@ impl Hepburn{
             "や" => "ya", "ヤ" => "ya", "ゆ" => "yu", "ユ" => "yu", "よ" => "yo", "ヨ" => "yo",
             "ら" => "ra", "ラ" => "ra", "り" => "ri", "リ" => "ri", "る" => "ru", "ル" => "ru",
             "れ" => "re", "レ" => "re", "ろ" => "ro", "ロ" => "ro",
-            "わ" => "wa", "ワ" => "wa", "ゐ" => "i", "を" => "o", "ヲ" => "o", "ん" => "n", "ン" => "n",
+            "わ" => "wa", "ワ" => "wa", "ゐ" => "i", "ヰ" => "i", "ゑ" => "e", "ヱ" => "e",
+            "を" => "o", "ヲ" => "o", "ん" => "n", "ン" => "n",
             "が" => "ga", "ガ" => "ga", "ぎ" => "gi", "ギ" => "gi", "ぐ" => "gu", "グ" => "gu",
             "げ" => "ge", "ゲ" => "ge", "ご" => "go", "ゴ" => "go", "ざ" => "za", "ザ" => "za",
             "じ" => "ji", "ジ" => "ji", "ず" => "zu", "ズ" => "zu",
@@ -138,6 +282,30 @@ impl Hepburn{
             "ぎょ" => "gyo", "ギョ" => "gyo",
             "じゃ" => "ja", "ジャ" => "ja", "じゅ" => "ju", "ジュ" => "ju",
             "じょ" => "jo", "ジョ" => "jo",
+            "ぢゃ" => "ja", "ヂャ" => "ja", "ぢゅ" => "ju", "ヂュ" => "ju",
+            "ぢょ" => "jo", "ヂョ" => "jo",
+            "ぁ" => "a", "ァ" => "a", "ぃ" => "i", "ィ" => "i", "ぅ" => "u", "ゥ" => "u",
+            "ぇ" => "e", "ェ" => "e", "ぉ" => "o", "ォ" => "o", "ゎ" => "wa", "ヮ" => "wa",
+            "ゔ" => "vu", "ヴ" => "vu", "ヵ" => "ka",
+            "てぃ" => "ti", "ティ" => "ti", "でぃ" => "di", "ディ" => "di",
+            "とぅ" => "tu", "トゥ" => "tu", "どぅ" => "du", "ドゥ" => "du",
+            "てゅ" => "tyu", "テュ" => "tyu", "でゅ" => "dyu", "デュ" => "dyu",
+            "ふぁ" => "fa", "ファ" => "fa", "ふぃ" => "fi", "フィ" => "fi",
+            "ふぇ" => "fe", "フェ" => "fe", "ふぉ" => "fo", "フォ" => "fo",
+            "ふゅ" => "fyu", "フュ" => "fyu",
+            "うぃ" => "wi", "ウィ" => "wi", "うぇ" => "we", "ウェ" => "we",
+            "うぉ" => "wo", "ウォ" => "wo", "いぇ" => "ye", "イェ" => "ye",
+            "しぇ" => "she", "シェ" => "she", "ちぇ" => "che", "チェ" => "che",
+            "じぇ" => "je", "ジェ" => "je", "すぃ" => "si", "スィ" => "si",
+            "ずぃ" => "zi", "ズィ" => "zi",
+            "つぁ" => "tsa", "ツァ" => "tsa", "つぃ" => "tsi", "ツィ" => "tsi",
+            "つぇ" => "tse", "ツェ" => "tse", "つぉ" => "tso", "ツォ" => "tso",
+            "くぁ" => "kwa", "クァ" => "kwa", "くぃ" => "kwi", "クィ" => "kwi",
+            "くぇ" => "kwe", "クェ" => "kwe", "くぉ" => "kwo", "クォ" => "kwo",
+            "ぐぁ" => "gwa", "グァ" => "gwa",
+            "ゔぁ" => "va", "ヴァ" => "va", "ゔぃ" => "vi", "ヴィ" => "vi",
+            "ゔぇ" => "ve", "ヴェ" => "ve", "ゔぉ" => "vo", "ヴォ" => "vo",
+            "ゔゅ" => "vyu", "ヴュ" => "vyu",
             "びゃ" => "bya", "ビャ" => "bya", "びゅ" => "byu", "ビュ" => "byu",
             "びょ" => "byo", "ビョ" => "byo",
             "ぴゃ" => "pya", "ピャ" => "pya", "ぴゅ" => "pyu", "ピュ" => "pyu",
@@ -157,18 +325,63 @@ impl Hepburn{
     }
 }
 
-pub fn map_kanjis(strings: &[String], subs: &[[String; 2]]) -> Vec<String>{
-    let mut replaceds = strings.to_vec();
-    for [replacee, replacant] in subs{
-        for (i, string) in replaceds.iter().enumerate(){
-            let new = string.replacen(replacee, replacant, 1);
-            if &new != string {
-                replaceds[i] = new;
-                break;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KanjiMatch{
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Finds where every kmap entry is substituted: the first occurrence of it's kanji that is not
+/// already taken by an earlier entry. Entries that can't be placed are `None`.
+pub fn match_kanjis(strings: &[String], subs: &[[String; 2]]) -> Vec<Option<KanjiMatch>>{
+    let mut taken: Vec<Vec<KanjiMatch>> = vec![Vec::new(); strings.len()];
+    subs.iter().map(|[replacee, _]| {
+        if replacee.is_empty() { return None; }
+        for (line, string) in strings.iter().enumerate(){
+            let free = string.match_indices(replacee.as_str())
+                .map(|(start, m)| KanjiMatch{ line, start, end: start + m.len() })
+                .find(|m| taken[line].iter().all(|t| m.end <= t.start || m.start >= t.end));
+            if let Some(m) = free{
+                taken[line].push(m);
+                return Some(m);
             }
         }
+        None
+    }).collect()
+}
+
+/// Splits a line into the text between the kmap matches, without a reading,
+/// and the matched kanji with their reading.
+pub fn kanji_segments<'a>(
+    string: &'a str, line: usize, matches: &[Option<KanjiMatch>], subs: &'a [[String; 2]]
+) -> Vec<(&'a str, Option<&'a str>)>{
+    let mut found = matches.iter().zip(subs)
+        .filter_map(|(m, [_, replacant])| m.filter(|m| m.line == line).map(|m| (m, replacant)))
+        .collect::<Vec<_>>();
+    found.sort_unstable_by_key(|(m, _)| m.start);
+    let mut segments = Vec::new();
+    let mut at = 0;
+    for (m, replacant) in found{
+        if at < m.start{
+            segments.push((&string[at..m.start], None));
+        }
+        segments.push((&string[m.start..m.end], Some(replacant.as_str())));
+        at = m.end;
     }
-    replaceds
+    if at < string.len(){
+        segments.push((&string[at..], None));
+    }
+    segments
+}
+
+pub fn map_kanjis(strings: &[String], subs: &[[String; 2]]) -> Vec<String>{
+    let matches = match_kanjis(strings, subs);
+    strings.iter().enumerate().map(|(i, string)|
+        kanji_segments(string, i, &matches, subs).into_iter()
+            .map(|(text, reading)| reading.unwrap_or(text))
+            .collect()
+    ).collect()
 }
 
 pub fn could_contain_kanji(strings: &[String]) -> bool{
@@ -181,34 +394,57 @@ pub fn could_contain_kanji(strings: &[String]) -> bool{
 }
 
 pub fn could_be_kanji(c: char) -> bool{
-    !is_latin(c) && !is_hiragana(c) && !is_katakana(c) && !is_punctuation(c) && !is_whitespace(c)
-}
-
-pub fn is_latin(c: char) -> bool{
-    "qgmlwyfubdstnriaeohzxcvjkpQGMLWYFUBDSTNRIAEOHZXCVJKP0123456789０１２３４５６７８９".contains(c)
+    matches!(Script::of(c), Script::Kanji | Script::Other)
 }
 
-pub fn is_hiragana(c: char) -> bool{
-    "あいうえおかきくけこさしすせそたちつてとなにぬねのはひふへほまみむめもやゆよらりるれろわをんがぎぐげござじずぜぞだぢづでどばびぶべぼぱぴぷぺぽゐゃゅょっ".contains(c)
-}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Script{ Hiragana, Katakana, Kanji, Latin, Punctuation, Whitespace, Other }
 
-pub fn is_katakana(c: char) -> bool{
-    "アイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワヲンガギグゲゴザジズゼゾダヂヅデドバビブベボパピプペポャュョッ".contains(c)
+impl Script{
+    /// Classifies a character by the Unicode block it is in.
+    pub fn of(c: char) -> Self{
+        match c{
+            c if c.is_whitespace() => Self::Whitespace,
+            // long vowel marks and middle dots sit in the kana blocks but are not kana
+            'ー' | 'ｰ' | '・' | '･' | '゠' | '゛' | '゜' => Self::Punctuation,
+            // iteration mark, closing mark, kanji zero and the counter ヶ need a reading
+            '々' | '〆' | '〇' | 'ヶ' => Self::Kanji,
+            '\u{3041}'..='\u{309f}' => Self::Hiragana,
+            '\u{30a0}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}' | '\u{ff66}'..='\u{ff9f}' =>
+                Self::Katakana,
+            '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}'
+                | '\u{20000}'..='\u{3134f}' => Self::Kanji,
+            'a'..='z' | 'A'..='Z' | '0'..='9'
+                | '\u{ff10}'..='\u{ff19}' | '\u{ff21}'..='\u{ff3a}' | '\u{ff41}'..='\u{ff5a}' =>
+                Self::Latin,
+            c if c.is_ascii_punctuation() => Self::Punctuation,
+            // Latin-1 punctuation, general punctuation, arrows, shapes and symbols such as ♪,
+            // CJK symbols and punctuation, vertical forms and full-width punctuation
+            '\u{a0}'..='\u{bf}' | '\u{2000}'..='\u{206f}' | '\u{2190}'..='\u{21ff}'
+                | '\u{2500}'..='\u{27bf}' | '\u{3000}'..='\u{303f}' | '\u{fe10}'..='\u{fe1f}'
+                | '\u{fe30}'..='\u{fe4f}' | '\u{ff01}'..='\u{ff0f}' | '\u{ff1a}'..='\u{ff20}'
+                | '\u{ff3b}'..='\u{ff40}' | '\u{ff5b}'..='\u{ff65}' => Self::Punctuation,
+            _ => Self::Other,
+        }
+    }
 }
 
 pub fn is_punctuation(c: char) -> bool{
-    "\"'-_=+`~,./<>?\\|[]{}!@#$%^&*()〜ー！？・「」、，。".contains(c)
+    Script::of(c) == Script::Punctuation
 }
 
 pub fn is_whitespace(c: char) -> bool{
-    " 　\t\n".contains(c)
+    Script::of(c) == Script::Whitespace
 }
 
 pub fn to_mora(c: char) -> usize{
-    if "ゃゅょャュョ 　〜！？・「」、。-_=+`~,./<>?\\|[]{}!@#$%^&*(\"'".contains(c) { return 0; }
-    if is_latin(c) { return 0; }
-    if is_whitespace(c) { return 0; }
-    1
+    // small kana only change the sound of the kana before them, like in きょ, ティ and ファ
+    if "ゃゅょゎぁぃぅぇぉャュョヮァィゥェォｬｭｮｧｨｩｪｫﾞﾟ".contains(c) { return 0; }
+    match Script::of(c){
+        Script::Hiragana | Script::Katakana | Script::Kanji => 1,
+        Script::Punctuation => usize::from(c == 'ー' || c == 'ｰ'),
+        _ => 0,
+    }
 }
 
 #[cfg(test)]
@@ -245,6 +481,93 @@ mod tests{
             &romanize("チャッ"),
             "chah"
         );
+        assert_eq!(
+            &romanize("いって"),
+            "itte"
+        );
+        assert_eq!(
+            &romanize("えっお"),
+            "eho"
+        );
+    }
+
+    #[test]
+    fn test_romanize_particles(){
+        let options = Romanization{ particles: true, ..Default::default() };
+        assert_eq!(
+            &romanize_with("ちょうしょく は じぶんで!! つくって ください!!", &options),
+            "choushoku wa jibunde!! tsukutte kudasai!!"
+        );
+        assert_eq!(
+            &romanize_with("がっこう へ いく", &options),
+            "gakkou e iku"
+        );
+        assert_eq!(
+            &romanize_with("ごはん を たべる は!", &options),
+            "gohan o taberu wa!"
+        );
+        assert_eq!(
+            &romanize_with("\\は なに は はー", &options),
+            "ha nani wa haa"
+        );
+        assert_eq!(
+            &romanize_with("\\は は", &Romanization::default()),
+            "ha ha"
+        );
+    }
+
+    #[test]
+    fn test_romanize_schemes(){
+        use RomanizationScheme::*;
+        assert_eq!(&romanize_scheme("いってキーまーす!!", HepburnMacrons), "ittekīmāsu!!");
+        assert_eq!(&romanize_scheme("ちょうしょく", HepburnMacrons), "chōshoku");
+        assert_eq!(&romanize_scheme("ゆうき おかあさん", HepburnMacrons), "yūki okāsan");
+        assert_eq!(&romanize_scheme("しゃしん ちず つなみ ふじ", Kunrei), "syasin tizu tunami huzi");
+        assert_eq!(&romanize_scheme("ちぢむ つづく まっちゃ", Kunrei), "tizimu tuzuku mattya");
+        assert_eq!(&romanize_scheme("ちぢむ つづく を", Nihon), "tidimu tuduku wo");
+        let options = Romanization{ scheme: Nihon, particles: true };
+        assert_eq!(&romanize_with("ほん を よむ", &options), "hon wo yomu");
+    }
+
+    #[test]
+    fn test_romanize_loanwords(){
+        assert_eq!(&romanize("パーティー"), "paatii");
+        assert_eq!(&romanize("ファイル フォーク"), "fairu fooku");
+        assert_eq!(&romanize("ヴァイオリン ウィンドウ"), "vaiorin windou");
+        assert_eq!(&romanize("チェック シェフ ジェット"), "chekku shefu jetto");
+        assert_eq!(&romanize("ディスク トゥーン デュエット"), "disuku tuun dyuetto");
+        assert_eq!(&romanize("あぁ ゔ"), "aa vu");
+        assert_eq!(&romanize_scheme("チェック", RomanizationScheme::Kunrei), "tyekku");
+    }
+
+    #[test]
+    fn test_script(){
+        assert_eq!(Script::of('あ'), Script::Hiragana);
+        assert_eq!(Script::of('ゞ'), Script::Hiragana);
+        assert_eq!(Script::of('ア'), Script::Katakana);
+        assert_eq!(Script::of('ｱ'), Script::Katakana);
+        assert_eq!(Script::of('日'), Script::Kanji);
+        assert_eq!(Script::of('㐂'), Script::Kanji);
+        assert_eq!(Script::of('々'), Script::Kanji);
+        assert_eq!(Script::of('ヶ'), Script::Kanji);
+        assert_eq!(Script::of('Ａ'), Script::Latin);
+        assert_eq!(Script::of('ｚ'), Script::Latin);
+        assert_eq!(Script::of('５'), Script::Latin);
+        assert_eq!(Script::of('　'), Script::Whitespace);
+        for c in "…『』（）～〜ー・「」！？♪―".chars(){
+            assert_eq!(Script::of(c), Script::Punctuation, "{}", c);
+        }
+        assert!(!could_contain_kanji(&["ｱｲｳ…『ＡＢＣ』（１２３）～".to_string()]));
+        assert!(could_contain_kanji(&["ひとびと 人々".to_string()]));
+    }
+
+    #[test]
+    fn test_romanize_iteration_marks(){
+        assert_eq!(&romanize("いすゞ こゝろ"), "isuzu kokoro");
+        assert_eq!(&romanize("バナヽ"), "banana");
+        assert_eq!(&romanize("ちゞむ"), "chijimu");
+        assert_eq!(&romanize_scheme("ちゞむ つゞく", RomanizationScheme::Kunrei), "tizimu tuzuku");
+        assert_eq!(&romanize_scheme("ちゞむ つゞく", RomanizationScheme::Nihon), "tidimu tuduku");
     }
 
     #[test]
@@ -343,6 +666,33 @@ mod tests{
         );
     }
 
+    #[test]
+    fn test_match_kanjis(){
+        let map = vec!
+            [
+                ("日", "につ"), ("今日", "きょう"), ("直", "ちょく"), ("雨", "あめ")
+            ]
+            .into_iter().map(|(a, b)| [a.to_string(), b.to_string()]).collect::<Vec<_>>();
+        assert_eq!(
+            match_kanjis(&["今日 日直".to_string()], &map),
+            vec![
+                Some(KanjiMatch{ line: 0, start: 3, end: 6 }),
+                None,
+                Some(KanjiMatch{ line: 0, start: 10, end: 13 }),
+                None,
+            ]
+        );
+        let map = vec![("幸", "こう"), ("幸", "さいわ")]
+            .into_iter().map(|(a, b)| [a.to_string(), b.to_string()]).collect::<Vec<_>>();
+        assert_eq!(
+            match_kanjis(&["不幸".to_string(), "幸い".to_string()], &map),
+            vec![
+                Some(KanjiMatch{ line: 0, start: 3, end: 6 }),
+                Some(KanjiMatch{ line: 1, start: 0, end: 3 }),
+            ]
+        );
+    }
+
     #[test]
     fn to_mora_test(){
         fn morae(string: &str) -> usize{
@@ -350,6 +700,8 @@ mod tests{
         }
         assert_eq!(morae("きょう"), 2);
         assert_eq!(morae("  いってキーまーす！"), 8);
+        assert_eq!(morae("パーティー"), 4);
+        assert_eq!(morae("ファイル"), 3);
     }
 
     #[test]
@@ -371,6 +723,8 @@ mod tests{
         assert_eq!(&res[13], "だ");
         assert_eq!(&res[14], "さ");
         assert_eq!(&res[15], "い");
+        let res = split_hirakata("パーティー ファン");
+        assert_eq!(res, vec!["パ", "ティ", "ファ", "ン"]);
     }
 
     #[test]
@@ -379,5 +733,6 @@ mod tests{
         let b = ["そうかんがえると".to_string()];
         assert!(could_contain_kanji(&a));
         assert!(!could_contain_kanji(&b));
+        assert!(!could_contain_kanji(&["パーティー ヴァイオリン ウィンドウ".to_string()]));
     }
 }
@@ -10,13 +10,17 @@ pub struct LangStats{
     rp: ReportHeader,
     kanji: HashMap<String, usize>,
     other: HashMap<String, usize>,
+    words: HashMap<String, usize>,
+    sfx: HashMap<String, usize>,
 }
 
-pub fn lang_stats_report(mut s: LangStats, doc: &mut String){
-    write_header(&mut s.rp, doc);
-
-    write_list(&s.other, "Hiragana/Katakana frequencies:", "", doc);
-    write_list(&s.kanji, "Kanji frequencies:", "", doc);
+pub fn lang_stats_report(s: LangStats) -> Report{
+    let mut report = Report::new(s.rp);
+    report.rankings.push(ranking("kana", "Hiragana/Katakana frequencies", &s.other));
+    report.rankings.push(ranking("kanji", "Kanji frequencies", &s.kanji));
+    report.rankings.push(ranking("words", "Word frequencies", &s.words));
+    report.rankings.push(ranking("sfx", "Sound effect frequencies", &s.sfx));
+    report
 }
 
 pub fn accumulate_lang_stats(chapter: Chapter, stats: &mut LangStats, log: &mut String){
@@ -34,6 +38,16 @@ pub fn accumulate_lang_stats(chapter: Chapter, stats: &mut LangStats, log: &mut
             for text in texts{
                 log_todo(&text, log);
                 let lines = text.lines.vectorize();
+                // sound effects are kept apart from the language
+                if text.kind == TextKind::Sfx{
+                    let replacements = map_kanjis(&lines, &text.kmap.vectorize());
+                    for (line, replaced) in lines.iter().zip(&replacements){
+                        for word in split_words(line, replaced){
+                            update(&mut stats.sfx, &word, |x| x + 1);
+                        }
+                    }
+                    continue;
+                }
                 let replacements = if let Some(kmap) = text.kmap{
                     let kmap = kmap.vectorize();
                     for [kanji, mapping] in &kmap{
@@ -54,6 +68,11 @@ pub fn accumulate_lang_stats(chapter: Chapter, stats: &mut LangStats, log: &mut
                 }
                 let morae = replacements.iter().flat_map(|line| line.chars())
                     .fold(0, |acc, c| acc + to_mora(c));
+                for (line, replaced) in lines.iter().zip(&replacements){
+                    for word in split_words(line, replaced){
+                        update(&mut stats.words, &word, |x| x + 1);
+                    }
+                }
                 for line in lines{
                     let split = split_hirakata(&line);
                     for c in split{
@@ -66,3 +85,34 @@ pub fn accumulate_lang_stats(chapter: Chapter, stats: &mut LangStats, log: &mut
     }
 }
 
+/// Splits a line into the words the transcriber separated with spaces.
+/// Words that had their kanji replaced are keyed as `"surface: reading"`.
+fn split_words(line: &str, replaced: &str) -> Vec<String>{
+    // long vowel marks belong to the word, like in キー and ええ〜
+    let trim = |c| c != 'ー' && c != '〜' && is_punctuation(c);
+    let surfaces = line.split_whitespace().collect::<Vec<_>>();
+    let readings = replaced.split_whitespace().collect::<Vec<_>>();
+    let aligned = surfaces.len() == readings.len();
+    surfaces.iter().enumerate().filter_map(|(i, surface)| {
+        let surface = surface.trim_matches(trim);
+        if surface.is_empty() { return None; }
+        let reading = if aligned { readings[i].trim_matches(trim) } else { surface };
+        if reading == surface{
+            Some(surface.to_string())
+        } else {
+            Some(format!("{}: {}", surface, reading))
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_split_words(){
+        assert_eq!(split_words("「キー を くれ〜！」", "「キー を くれ〜！」"), vec!["キー", "を", "くれ〜"]);
+        assert_eq!(split_words("鍵 だ！", "かぎ だ！"), vec!["鍵: かぎ", "だ"]);
+        assert_eq!(split_words("ドーン！！", "ドーン！！"), vec!["ドーン"]);
+    }
+}
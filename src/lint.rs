@@ -0,0 +1,110 @@
+use crate::structure::*;
+use crate::japanese::*;
+use crate::report::*;
+
+/// Checks every kmap against the lines it belongs to.
+pub fn lint_chapter(chapter: &Chapter, issues: &mut Vec<Issue>){
+    let mut location = Location::new(chapter);
+    for picture in &chapter.pic{
+        location.advance(picture);
+        let texts = if let Some(texts) = &picture.text{ texts } else { continue; };
+        for (n, text) in texts.iter().enumerate(){
+            let mut issue = |message: String| issues.push(Issue{
+                location,
+                text: Some(n + 1),
+                message,
+            });
+            let lines = text.lines.clone().vectorize();
+            let kmap = text.kmap.clone().vectorize();
+            for message in lint_kmap(&lines, &kmap){
+                issue(message);
+            }
+        }
+    }
+}
+
+fn lint_kmap(lines: &[String], kmap: &[[String; 2]]) -> Vec<String>{
+    let mut messages = Vec::new();
+    let entry = |i: usize| format!("kmap entry {} [\"{}\", \"{}\"]", i + 1, kmap[i][0], kmap[i][1]);
+    let matches = match_kanjis(lines, kmap);
+
+    for (i, [kanji, reading]) in kmap.iter().enumerate(){
+        if reading.trim().is_empty(){
+            messages.push(format!("{} has an empty reading.", entry(i)));
+        }
+        if matches[i].is_some() { continue; }
+        // the kanji are there, so an earlier entry took (part of) them
+        let taker = lines.iter().enumerate().find_map(|(line, string)| {
+            let start = string.find(kanji.as_str())?;
+            let end = start + kanji.len();
+            matches[..i].iter().position(|m|
+                m.is_some_and(|m| m.line == line && m.start < end && m.end > start)
+            )
+        });
+        if kanji.is_empty(){
+            messages.push(format!("{} has no kanji.", entry(i)));
+        } else if let Some(taker) = taker{
+            messages.push(format!(
+                "{} never matched, {} took it's kanji first.", entry(i), entry(taker)
+            ));
+        } else {
+            messages.push(format!("{} never matched, it does not occur in the lines.", entry(i)));
+        }
+    }
+
+    let position = |i: usize| matches[i].map(|m| (m.line, m.start));
+    for i in 0..kmap.len(){
+        let pos = if let Some(pos) = position(i){ pos } else { continue; };
+        if let Some(earlier) = (0..i).find(|&j| position(j).is_some_and(|p| p > pos)){
+            messages.push(format!(
+                "{} is listed after {} but matched before it.", entry(i), entry(earlier)
+            ));
+        }
+    }
+
+    let mut unmapped = Vec::new();
+    for c in map_kanjis(lines, kmap).iter().flat_map(|line| line.chars()){
+        if could_be_kanji(c) && !unmapped.contains(&c){
+            unmapped.push(c);
+        }
+    }
+    if !unmapped.is_empty(){
+        messages.push(format!(
+            "kanji left unmapped: {}.",
+            unmapped.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn lint(lines: &[&str], kmap: &[[&str; 2]]) -> Vec<String>{
+        let lines = lines.iter().map(|line| line.to_string()).collect::<Vec<_>>();
+        let kmap = kmap.iter()
+            .map(|[kanji, reading]| [kanji.to_string(), reading.to_string()])
+            .collect::<Vec<_>>();
+        lint_kmap(&lines, &kmap)
+    }
+
+    #[test]
+    fn test_lint_kmap(){
+        assert!(lint(&["今日は 日"], &[["今日", "きょう"], ["日", "ひ"]]).is_empty());
+        assert_eq!(lint(&["今日は日"], &[["日", "ひ"], ["今日", "きょう"]]), vec![
+            "kmap entry 2 [\"今日\", \"きょう\"] never matched, \
+            kmap entry 1 [\"日\", \"ひ\"] took it's kanji first.",
+            "kanji left unmapped: 今, 日.",
+        ]);
+        assert_eq!(lint(&["朝", "夜"], &[["夜", "よる"], ["朝", "あさ"]]), vec![
+            "kmap entry 2 [\"朝\", \"あさ\"] is listed after kmap entry 1 [\"夜\", \"よる\"] \
+            but matched before it.",
+        ]);
+        assert_eq!(lint(&["朝 と 夜"], &[["朝", " "], ["昼", "ひる"]]), vec![
+            "kmap entry 1 [\"朝\", \" \"] has an empty reading.",
+            "kmap entry 2 [\"昼\", \"ひる\"] never matched, it does not occur in the lines.",
+            "kanji left unmapped: 夜.",
+        ]);
+    }
+}
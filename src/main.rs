@@ -4,11 +4,35 @@ mod transcribe;
 mod language;
 mod stats;
 mod report;
+mod config;
+mod characters;
+mod series;
+mod html;
+mod anki;
+mod normalize;
+mod memory;
+mod graph;
+mod sfx;
+mod diagnostics;
+mod lint;
+mod validate;
 
 use structure::*;
 use transcribe::*;
 use language::*;
 use stats::*;
+use config::*;
+use series::*;
+use html::*;
+use anki::*;
+use normalize::*;
+use memory::*;
+use graph::*;
+use sfx::*;
+use diagnostics::*;
+use lint::*;
+use validate::*;
+use report::*;
 
 use clap::Parser;
 
@@ -23,35 +47,111 @@ struct Args{
     mode: Mode,
     #[clap(short='o', long, value_enum, default_value_t=OutputMode::default())]
     outputmode: OutputMode,
-    #[clap(short='l', long, default_value_t=true)]
+    #[clap(short='f', long, value_enum)]
+    format: Option<Format>,
+    #[clap(long, value_enum, default_value_t=Cards::default())]
+    cards: Cards,
+    #[clap(long)]
+    paste: bool,
+    #[clap(long, value_enum)]
+    breakdown: Option<Breakdown>,
+    #[clap(long, value_enum, default_value_t=NodeWeight::default())]
+    weight: NodeWeight,
+    #[clap(long)]
+    hide_non_diegetic: bool,
+    #[clap(short='r', long, value_enum)]
+    romanization: Option<japanese::RomanizationScheme>,
+    #[clap(short='l', long, action=clap::ArgAction::Set, default_value_t=true)]
     log: bool,
     #[clap(short='d', long, value_parser)]
     outputdir: Option<PathBuf>,
+    #[clap(short='c', long, value_parser)]
+    config: Option<PathBuf>,
+    #[clap(short='s', long, value_parser)]
+    series: Option<PathBuf>,
     #[clap(required = true)]
     inputfiles: Vec<PathBuf>,
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
-enum Mode { #[default] Transcribe, Stats, Language }
+enum Mode{
+    #[default] Transcribe, Stats, Language, Lint, Validate, Anki, Suggest, Readings, Graph, Sfx
+}
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
 enum OutputMode { #[default] Stdout, File }
 
+impl Mode{
+    /// The formats the mode can write, the default first.
+    fn formats(&self) -> &'static [Format]{
+        match self{
+            Self::Transcribe => &[Format::Markdown, Format::Html],
+            Self::Stats | Self::Language =>
+                &[Format::Text, Format::Markdown, Format::Json, Format::Csv],
+            Self::Graph => &[Format::Dot, Format::Graphml],
+            Self::Sfx => &[Format::Markdown, Format::Text, Format::Json, Format::Csv],
+            _ => &[],
+        }
+    }
+}
+
 fn main() {
     let args = Args::parse();
     if args.inputfiles.is_empty(){
-        println!("No input files received!");
+        eprintln!("No input files received!");
         return;
     }
     let mut log = String::new();
     let mut doc = String::new();
+    let formats = args.mode.formats();
+    let format = match args.format{
+        None => formats.first().copied().unwrap_or(Format::Markdown),
+        Some(format) if formats.contains(&format) => format,
+        Some(format) => {
+            eprintln!(
+                "The {} mode can not be written as {}.",
+                format!("{:?}", args.mode).to_lowercase(), format!("{:?}", format).to_lowercase()
+            );
+            std::process::exit(1);
+        },
+    };
 
     let mut fileroot = args.inputfiles[0].clone();
-    let mut chapters = args.inputfiles.into_iter()
-        .map(|f| (get_chapter(&f), f))
-        .filter(|(c, _)| c.is_some())
-        .map(|(c, f)| (c.unwrap(), f))
-        .collect::<Vec<_>>();
+    let series = get_series(args.series.as_ref(), &fileroot);
+    let config = get_config(args.config.as_ref(), &fileroot);
+    let (mut series, config) = match (series, config){
+        (Ok(series), Ok(config)) => (series.unwrap_or_default(), config),
+        (Err(error), _) | (_, Err(error)) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut config = config.or(series.config.take()).unwrap_or_default();
+    merge_series(&mut series, &mut config);
+    if let Some(scheme) = args.romanization{
+        config.romanization.scheme = scheme;
+    }
+    if args.hide_non_diegetic{
+        config.hide_non_diegetic = true;
+    }
+
+    let mut failed = 0;
+    let mut chapters = Vec::new();
+    for file in args.inputfiles{
+        let name = file.file_name().and_then(|name| name.to_str());
+        if name == Some(SERIES_FILE) || name == Some(CONFIG_FILE) { continue; }
+        match get_chapter(&file){
+            Ok(mut chapter) => {
+                inherit_series(&mut chapter, &series, &mut log);
+                normalize_chapter(&mut chapter, &config.normalization);
+                chapters.push((chapter, file));
+            },
+            Err(error) => {
+                eprintln!("{}", error);
+                failed += 1;
+            },
+        }
+    }
     chapters.sort_by(|a, b|
         a.0.volume.cmp(&b.0.volume)
         .then(a.0.chapter.cmp(&b.0.chapter))
@@ -63,18 +163,67 @@ fn main() {
         Mode::Transcribe => {
             for (chapter, file) in chapters{
                 doc.clear();
-                write_transcription(chapter, &mut doc, &mut log);
-                write_output(args.outputmode, &args.outputdir, file, &doc);
+                if format == Format::Html{
+                    write_html_transcription(
+                        chapter, series.romanized.as_deref(), &config, &mut doc, &mut log
+                    );
+                } else {
+                    write_transcription(
+                        chapter, series.romanized.as_deref(), &config, &mut doc, &mut log
+                    );
+                }
+                write_output(args.outputmode, &args.outputdir, file, format.extension(), &doc);
             }
         },
         Mode::Stats => {
             let mut stats = Stats::default();
+            let mut timeline = Timeline::new(args.breakdown.unwrap_or_default());
             for (chapter, _) in chapters{
-                accumulate_stats(chapter, &mut stats, &mut log);
+                if args.breakdown.is_some(){
+                    accumulate_timeline(&chapter, &config, &mut timeline);
+                }
+                accumulate_stats(chapter, &config, &mut stats, &mut log);
             }
             fileroot.set_file_name("stats");
-            stats_report(stats, &mut doc);
-            write_output(args.outputmode, &args.outputdir, fileroot, &doc);
+            let mut report = stats_report(stats, &config);
+            if args.breakdown.is_some(){
+                report.periods = timeline_periods(&timeline, &config);
+            }
+            write_report(&report, format, &mut doc);
+            if args.breakdown.is_some() && matches!(format, Format::Text | Format::Markdown){
+                timeline_tables(&timeline, &config, &mut doc);
+            }
+            let timeline_file = fileroot.with_file_name("timeline");
+            write_output(args.outputmode, &args.outputdir, fileroot, format.extension(), &doc);
+            // on stdout it would follow the report, which already has the periods
+            if args.breakdown.is_some() && args.outputmode == OutputMode::File{
+                let mut csv = String::new();
+                timeline_csv(&timeline, &config, &mut csv);
+                write_output(args.outputmode, &args.outputdir, timeline_file, "csv", &csv);
+            }
+        },
+        Mode::Graph => {
+            let mut stats = Stats::default();
+            for (chapter, _) in chapters{
+                accumulate_stats(chapter, &config, &mut stats, &mut log);
+            }
+            let graph = interaction_graph(&stats, &config, args.weight);
+            if format == Format::Graphml{
+                write_graphml(&graph, &mut doc);
+            } else {
+                write_dot(&graph, &mut doc);
+            }
+            fileroot.set_file_name("characters");
+            write_output(args.outputmode, &args.outputdir, fileroot, format.extension(), &doc);
+        },
+        Mode::Sfx => {
+            let mut glossary = Glossary::default();
+            for (chapter, _) in &chapters{
+                accumulate_glossary(chapter, &mut glossary, &mut log);
+            }
+            fileroot.set_file_name("sfx");
+            write_report(&glossary_report(&glossary, &config), format, &mut doc);
+            write_output(args.outputmode, &args.outputdir, fileroot, format.extension(), &doc);
         },
         Mode::Language => {
             let mut stats = LangStats::default();
@@ -82,30 +231,78 @@ fn main() {
                 accumulate_lang_stats(chapter, &mut stats, &mut log);
             }
             fileroot.set_file_name("stats");
-            lang_stats_report(stats, &mut doc);
-            write_output(args.outputmode, &args.outputdir, fileroot, &doc);
+            write_report(&lang_stats_report(stats), format, &mut doc);
+            write_output(args.outputmode, &args.outputdir, fileroot, format.extension(), &doc);
+        },
+        Mode::Lint => {
+            let mut issues = Vec::new();
+            for (chapter, _) in chapters{
+                lint_chapter(&chapter, &mut issues);
+            }
+            fileroot.set_file_name("lint");
+            write_issues(&issues, &mut doc);
+            write_output(args.outputmode, &args.outputdir, fileroot, "md", &doc);
+        },
+        Mode::Validate => {
+            let mut issues = Vec::new();
+            for (chapter, _) in chapters{
+                validate_chapter(&chapter, &config, &mut issues);
+            }
+            fileroot.set_file_name("validation");
+            write_issues(&issues, &mut doc);
+            write_output(args.outputmode, &args.outputdir, fileroot, "md", &doc);
+        },
+        Mode::Anki => {
+            let mut deck = Deck::default();
+            for (chapter, _) in chapters{
+                accumulate_deck(chapter, &config, args.cards, &mut deck, &mut log);
+            }
+            fileroot.set_file_name("anki");
+            write_deck(&deck, &mut doc);
+            write_output(args.outputmode, &args.outputdir, fileroot, "tsv", &doc);
+        },
+        Mode::Suggest => {
+            let mut memory = ReadingMemory::default();
+            for (chapter, _) in &chapters{
+                memory.remember(chapter);
+            }
+            for (chapter, _) in &chapters{
+                write_suggestions(chapter, &memory, args.paste, &mut doc);
+            }
+            fileroot.set_file_name("suggestions");
+            write_output(args.outputmode, &args.outputdir, fileroot, "md", &doc);
+        },
+        Mode::Readings => {
+            let mut memory = ReadingMemory::default();
+            for (chapter, _) in &chapters{
+                memory.remember(chapter);
+            }
+            fileroot.set_file_name("readings");
+            write_readings(&memory, &mut doc);
+            write_output(args.outputmode, &args.outputdir, fileroot, "md", &doc);
         },
     }
+    // on stderr, so it does not end up in a report or graph written to stdout
     if args.log {
-        println!("{}", log);
+        eprint!("{}", log);
+    }
+    if failed > 0{
+        eprintln!("{} file(s) could not be read or parsed.", failed);
+        std::process::exit(1);
     }
 }
 
-fn get_chapter(file: &PathBuf) -> Option<Chapter>{
-    let contents = match fs::read_to_string(file){
-        Ok(contents) => contents,
-        Err(error) => {
-            println!("Could not read file: \"{}\".\n\tError: {}", file.display(), error);
-            return None;
-        }
-    };
-    match toml::from_str::<Chapter>(&contents){
-        Ok(chapter) => Some(chapter),
-        Err(error) => panic!("{} (error position is an estimation!)", error),
-    }
+fn get_chapter(file: &PathBuf) -> Result<Chapter, String>{
+    let contents = fs::read_to_string(file).map_err(|error|
+        format!("Could not read file: \"{}\".\n\tError: {}", file.display(), error)
+    )?;
+    toml::from_str::<Chapter>(&contents).map_err(|error| parse_diagnostic(file, &contents, &error))
 }
 
-fn write_output(outputmode: OutputMode, outputdir: &Option<PathBuf>, mut file: PathBuf, doc: &str){
+fn write_output(
+    outputmode: OutputMode, outputdir: &Option<PathBuf>, mut file: PathBuf, extension: &str,
+    doc: &str
+){
     if outputmode == OutputMode::File{
         if let Some(outdir) = outputdir{
             let filename = file.file_name().expect("rip").to_os_string();
@@ -113,16 +310,16 @@ fn write_output(outputmode: OutputMode, outputdir: &Option<PathBuf>, mut file: P
             file.push(outdir);
             file.push(filename);
         }
-        file.set_extension("md");
+        file.set_extension(extension);
         let mut outfile = match fs::File::create(&file){
             Ok(outfile) => outfile,
             Err(error) => {
-                println!("Could not create file: \"{}\".\n\tError: {}", file.display(), error);
+                eprintln!("Could not create file: \"{}\".\n\tError: {}", file.display(), error);
                 return;
             }
         };
         if let Err(error) = write!(outfile, "{}", doc){
-            println!("Could not write to file: \"{}\".\n\tError: {}", file.display(), error);
+            eprintln!("Could not write to file: \"{}\".\n\tError: {}", file.display(), error);
         }
     } else {
         println!("{}", doc);
@@ -0,0 +1,255 @@
+use crate::structure::*;
+use crate::japanese::*;
+use crate::report::*;
+
+use std::fmt::Write;
+use std::collections::HashMap;
+
+/// How often a reading was given to some kanji, and where it was given first.
+#[derive(Debug, Clone, Copy)]
+pub struct Reading{
+    pub count: usize,
+    pub first: Location,
+}
+
+/// Every reading the kmaps gave to every kanji sequence.
+#[derive(Debug, Clone, Default)]
+pub struct ReadingMemory{
+    pub readings: HashMap<String, HashMap<String, Reading>>,
+    longest: usize,
+}
+
+impl ReadingMemory{
+    /// Remembers the kmap entries of a chapter that match their lines.
+    pub fn remember(&mut self, chapter: &Chapter){
+        let mut location = Location::new(chapter);
+        for picture in &chapter.pic{
+            location.advance(picture);
+            for text in picture.text.iter().flatten(){
+                let lines = text.lines.clone().vectorize();
+                let kmap = text.kmap.clone().vectorize();
+                for (m, [kanji, reading]) in match_kanjis(&lines, &kmap).iter().zip(&kmap){
+                    if m.is_none() || reading.trim().is_empty() { continue; }
+                    self.longest = self.longest.max(kanji.chars().count());
+                    self.readings.entry(kanji.clone()).or_default()
+                        .entry(reading.clone())
+                        .or_insert(Reading{ count: 0, first: location })
+                        .count += 1;
+                }
+            }
+        }
+    }
+
+    /// The readings of a kanji sequence, the most used first.
+    pub fn candidates(&self, kanji: &str) -> Vec<(&str, Reading)>{
+        let mut candidates = self.readings.get(kanji).into_iter().flatten()
+            .map(|(reading, r)| (reading.as_str(), *r))
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0)));
+        candidates
+    }
+
+    /// Splits the kanji in a string into the longest sequences that have a reading. Kanji that
+    /// never had one are kept together as a run.
+    pub fn split<'a>(&self, string: &'a str) -> Vec<&'a str>{
+        let chars = string.char_indices().collect::<Vec<_>>();
+        let byte = |i: usize| chars.get(i).map(|(b, _)| *b).unwrap_or(string.len());
+        let known = |i: usize| (1..=self.longest.min(chars.len() - i)).rev()
+            .find(|len| self.readings.contains_key(&string[byte(i)..byte(i + len)]));
+        let mut pieces = Vec::new();
+        let mut i = 0;
+        while i < chars.len(){
+            if !could_be_kanji(chars[i].1){
+                i += 1;
+            } else if let Some(len) = known(i){
+                pieces.push(&string[byte(i)..byte(i + len)]);
+                i += len;
+            } else {
+                let start = i;
+                i += 1;
+                while i < chars.len() && could_be_kanji(chars[i].1) && known(i).is_none(){
+                    i += 1;
+                }
+                pieces.push(&string[byte(start)..byte(i)]);
+            }
+        }
+        pieces
+    }
+}
+
+/// Writes every kanji sequence with the readings it was given, the most used kanji first.
+/// A reading is marked as a possible typo when the kanji has one other reading that is used
+/// at least `DOMINANT` times, while it is used only once.
+pub fn write_readings(memory: &ReadingMemory, doc: &mut String){
+    const DOMINANT: usize = 3;
+    let mut kanjis = memory.readings.iter()
+        .map(|(kanji, readings)| (kanji, readings.values().map(|r| r.count).sum::<usize>()))
+        .collect::<Vec<_>>();
+    kanjis.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let mut typos = 0;
+    let _ = writeln!(doc, "Kanji readings:");
+    for (kanji, total) in kanjis{
+        let candidates = memory.candidates(kanji);
+        let outlier = match candidates.as_slice(){
+            [(_, dominant), (outlier, rare)] if dominant.count >= DOMINANT && rare.count == 1 =>
+                Some(*outlier),
+            _ => None,
+        };
+        let _ = writeln!(doc, "\t{}: {} time(s), {} reading(s)", kanji, total, candidates.len());
+        for (reading, r) in candidates{
+            let _ = write!(doc, "\t\t{}: {} time(s), first at {}", reading, r.count, r.first);
+            if outlier == Some(reading){
+                typos += 1;
+                let _ = write!(doc, " (possible typo)");
+            }
+            let _ = writeln!(doc);
+        }
+    }
+    let _ = writeln!(doc, "{} possible typo(s) found.", typos);
+}
+
+/// Writes the likely readings of the kanji that are missing from the kmap of every text.
+/// With `paste` it writes the whole kmap of those texts instead, with the most used readings
+/// filled in and the unknown ones left empty.
+pub fn write_suggestions(chapter: &Chapter, memory: &ReadingMemory, paste: bool, doc: &mut String){
+    let mut location = Location::new(chapter);
+    for picture in &chapter.pic{
+        location.advance(picture);
+        for (n, text) in picture.text.iter().flatten().enumerate(){
+            let lines = text.lines.clone().vectorize();
+            let kmap = text.kmap.clone().vectorize();
+            let matches = match_kanjis(&lines, &kmap);
+            let mut entries = Vec::new();
+            let mut unmapped = Vec::new();
+            for (i, line) in lines.iter().enumerate(){
+                for (segment, reading) in kanji_segments(line, i, &matches, &kmap){
+                    if let Some(reading) = reading{
+                        entries.push([segment, reading]);
+                        continue;
+                    }
+                    for kanji in memory.split(segment){
+                        let candidates = memory.candidates(kanji);
+                        entries.push([kanji, candidates.first().map_or("", |(r, _)| r)]);
+                        unmapped.push((kanji, candidates));
+                    }
+                }
+            }
+            if unmapped.is_empty() { continue; }
+            if paste{
+                let _ = writeln!(doc, "# {}, text {}", location, n + 1);
+                // quoted by toml, as Debug escapes like \u{3099} are not valid toml
+                let quote = |string: &str| toml::Value::String(string.to_string()).to_string();
+                let entries = entries.iter()
+                    .map(|[kanji, reading]| format!("[{}, {}]", quote(kanji), quote(reading)))
+                    .collect::<Vec<_>>();
+                let _ = writeln!(doc, "kmap = [{}]", entries.join(", "));
+                continue;
+            }
+            let _ = writeln!(doc, "{}, text {}:", location, n + 1);
+            for (kanji, candidates) in unmapped{
+                if candidates.is_empty(){
+                    let _ = writeln!(doc, "- {}: no known readings", kanji);
+                } else {
+                    let candidates = candidates.iter()
+                        .map(|(reading, r)| format!("{} ({})", reading, r.count))
+                        .collect::<Vec<_>>();
+                    let _ = writeln!(doc, "- {}: {}", kanji, candidates.join(", "));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_split(){
+        let chapter = toml::from_str::<Chapter>(r#"
+            title = "t"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            [[pic.text]]
+            from = "a"
+            lines = ["学校の先生", "先生"]
+            kmap = [["学校", "がっこう"], ["先生", "せんせい"], ["先生", "せんせ"]]
+            [[pic.text]]
+            from = "a"
+            lines = ["学生"]
+            kmap = ["学", "がく"]
+        "#).unwrap();
+        let mut memory = ReadingMemory::default();
+        memory.remember(&chapter);
+        assert_eq!(memory.split("学校で先生と校長"), vec!["学校", "先生", "校長"]);
+        assert_eq!(memory.split("学生だ"), vec!["学", "生"]);
+        let candidates = memory.candidates("先生").into_iter()
+            .map(|(reading, r)| (reading, r.count))
+            .collect::<Vec<_>>();
+        assert_eq!(candidates, vec![("せんせ", 1), ("せんせい", 1)]);
+        assert!(memory.candidates("校長").is_empty());
+    }
+
+    #[test]
+    fn test_readings(){
+        let chapter = toml::from_str::<Chapter>(r#"
+            title = "t"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            page = 2
+            [[pic.text]]
+            from = "a"
+            lines = ["日日日", "日"]
+            kmap = [["日", "ひ"], ["日", "ひ"], ["日", "ひ"], ["日", "にち"]]
+            [[pic]]
+            [[pic.text]]
+            from = "a"
+            lines = ["月月"]
+            kmap = [["月", "つき"], ["月", "げつ"]]
+        "#).unwrap();
+        let mut memory = ReadingMemory::default();
+        memory.remember(&chapter);
+        let mut doc = String::new();
+        write_readings(&memory, &mut doc);
+        assert_eq!(doc, concat!(
+            "Kanji readings:\n",
+            "\t日: 4 time(s), 2 reading(s)\n",
+            "\t\tひ: 3 time(s), first at volume 1, chapter 1, page 2, picture 1\n",
+            "\t\tにち: 1 time(s), first at volume 1, chapter 1, page 2, picture 1 (possible typo)\n",
+            "\t月: 2 time(s), 2 reading(s)\n",
+            "\t\tげつ: 1 time(s), first at volume 1, chapter 1, page 2, picture 2\n",
+            "\t\tつき: 1 time(s), first at volume 1, chapter 1, page 2, picture 2\n",
+            "1 possible typo(s) found.\n",
+        ));
+    }
+
+    #[test]
+    fn test_paste(){
+        let chapter = toml::from_str::<Chapter>(r#"
+            title = "t"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            page = 1
+            [[pic.text]]
+            from = "a"
+            lines = ["学校"]
+            kmap = ["学校", "か\u3099っこう"]
+            [[pic.text]]
+            from = "a"
+            lines = ["学校の\"先生\""]
+            kmap = ["先生", "せんせい"]
+        "#).unwrap();
+        let mut memory = ReadingMemory::default();
+        memory.remember(&chapter);
+        let mut doc = String::new();
+        write_suggestions(&chapter, &memory, true, &mut doc);
+        let pasted = doc.lines().nth(1).unwrap();
+        let kmap = toml::from_str::<toml::Table>(pasted).unwrap()["kmap"].clone();
+        assert_eq!(kmap, toml::Value::try_from(vec![
+            ["学校", "か\u{3099}っこう"], ["先生", "せんせい"]
+        ]).unwrap());
+    }
+}
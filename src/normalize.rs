@@ -0,0 +1,124 @@
+use crate::structure::*;
+use crate::japanese::*;
+
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct Normalization{
+    /// ｶﾀｶﾅ to カタカナ.
+    pub halfwidth_katakana: bool,
+    /// Ａｂｃ１２３ to Abc123.
+    pub fullwidth_alphanumerics: bool,
+    /// ASCII and variant punctuation to the Japanese forms: ! ? ～ 〰 to ！ ？ 〜,
+    /// ｡｢｣､ to 。「」、 and hyphens after kana to ー. Long dashes like ―― are kept.
+    pub punctuation: bool,
+}
+
+impl Default for Normalization{
+    fn default() -> Self{
+        Self{
+            halfwidth_katakana: true,
+            fullwidth_alphanumerics: false,
+            punctuation: true,
+        }
+    }
+}
+
+/// Normalizes the Japanese text of a chapter: the lines and kmaps of every text.
+pub fn normalize_chapter(chapter: &mut Chapter, norm: &Normalization){
+    fn normalize_all(strings: &mut OneOrMore<String>, norm: &Normalization){
+        match strings{
+            Either::This(string) => *string = normalize(string, norm),
+            Either::That(strings) => strings.iter_mut()
+                .for_each(|string| *string = normalize(string, norm)),
+        }
+    }
+    for text in chapter.pic.iter_mut().flat_map(|pic| pic.text.iter_mut().flatten()){
+        normalize_all(&mut text.lines, norm);
+        match &mut text.kmap{
+            Some(Either::This(pair)) => pair.iter_mut()
+                .for_each(|string| *string = normalize(string, norm)),
+            Some(Either::That(pairs)) => pairs.iter_mut().flatten()
+                .for_each(|string| *string = normalize(string, norm)),
+            None => {},
+        }
+    }
+}
+
+pub fn normalize(string: &str, norm: &Normalization) -> String{
+    let mut res = String::new();
+    for c in string.chars(){
+        let prev = res.chars().last();
+        let c = match c{
+            'ﾞ' | 'ﾟ' if norm.halfwidth_katakana => {
+                if let Some(composed) = prev.and_then(|prev| compose(prev, c == 'ﾟ')){
+                    res.pop();
+                    composed
+                } else if c == 'ﾞ' { '゛' } else { '゜' }
+            },
+            '\u{ff66}'..='\u{ff9d}' if norm.halfwidth_katakana => {
+                const HALF: &str = concat!(
+                    "ｦｧｨｩｪｫｬｭｮｯｰｱｲｳｴｵｶｷｸｹｺｻｼｽｾｿﾀﾁﾂﾃﾄ",
+                    "ﾅﾆﾇﾈﾉﾊﾋﾌﾍﾎﾏﾐﾑﾒﾓﾔﾕﾖﾗﾘﾙﾚﾛﾜﾝ"
+                );
+                const FULL: &str = concat!(
+                    "ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテト",
+                    "ナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン"
+                );
+                HALF.chars().position(|h| h == c)
+                    .and_then(|i| FULL.chars().nth(i))
+                    .unwrap_or(c)
+            },
+            '\u{ff10}'..='\u{ff19}' | '\u{ff21}'..='\u{ff3a}' | '\u{ff41}'..='\u{ff5a}'
+                if norm.fullwidth_alphanumerics =>
+                char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+            '!' if norm.punctuation => '！',
+            '?' if norm.punctuation => '？',
+            '~' | '～' | '〰' if norm.punctuation => '〜',
+            '｡' if norm.punctuation => '。',
+            '｢' if norm.punctuation => '「',
+            '｣' if norm.punctuation => '」',
+            '､' if norm.punctuation => '、',
+            '･' if norm.punctuation => '・',
+            '-' | '－' | '‐' | 'ｰ' if norm.punctuation
+                && prev.is_some_and(|prev| prev == 'ー'
+                    || matches!(Script::of(prev), Script::Hiragana | Script::Katakana)) => 'ー',
+            c => c,
+        };
+        res.push(c);
+    }
+    res
+}
+
+// Combines a katakana with a (semi) voiced sound mark, like カ and ﾞ into ガ.
+fn compose(kana: char, semi: bool) -> Option<char>{
+    let offset = match (kana, semi){
+        ('ウ', false) => return Some('ヴ'),
+        ('ハ' | 'ヒ' | 'フ' | 'ヘ' | 'ホ', true) => 2,
+        (kana, false) if "カキクケコサシスセソタチツテトハヒフヘホ".contains(kana) => 1,
+        _ => return None,
+    };
+    char::from_u32(kana as u32 + offset)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_normalize(){
+        let norm = Normalization::default();
+        assert_eq!(&normalize("ﾊﾟｰﾃｨｰ ｶﾞｯｺｳ ｳﾞｧ", &norm), "パーティー ガッコウ ヴァ");
+        assert_eq!(&normalize("すご-い!! ほんと? ええ～ 〰", &norm), "すごーい！！ ほんと？ ええ〜 〜");
+        assert_eq!(&normalize("｢あ､ そう｡｣", &norm), "「あ、 そう。」");
+        assert_eq!(&normalize("Ａ１-Ｂ", &norm), "Ａ１-Ｂ");
+        assert_eq!(&normalize("そんな―― え—", &norm), "そんな―― え—");
+        let norm = Normalization{ fullwidth_alphanumerics: true, ..Default::default() };
+        assert_eq!(&normalize("Ａ１ ｚ", &norm), "A1 z");
+        let norm = Normalization{ punctuation: false, ..Default::default() };
+        assert_eq!(&normalize("ｽｺﾞｲ!", &norm), "スゴイ!");
+        let norm = Normalization{ halfwidth_katakana: false, ..Default::default() };
+        assert_eq!(&normalize("スｰパｰ", &norm), "スーパー");
+    }
+}
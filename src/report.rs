@@ -1,9 +1,11 @@
 use crate::structure::*;
 
+use serde::Serialize;
+
 use std::fmt::Write;
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ReportHeader{
     pub manga: String,
     pub volumes: Vec<usize>,
@@ -12,46 +14,501 @@ pub struct ReportHeader{
     pub morae: usize,
 }
 
-pub fn write_header(h: &mut ReportHeader, doc: &mut String){
-    let _ = writeln!(doc, "Manga: {}", h.manga);
-    let _ = write!(doc, "Volumes: ");
+/// Where in the manga something is, as far as it can be known while walking through the pictures.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Location{
+    pub volume: usize,
+    pub chapter: usize,
+    pub subchapter: Option<f32>,
+    pub page: usize,
+    pub picture: usize,
+}
+
+impl Location{
+    pub fn new(chapter: &Chapter) -> Self{
+        Self{
+            volume: chapter.volume,
+            chapter: chapter.chapter,
+            subchapter: chapter.subchapter,
+            page: 0,
+            picture: 0,
+        }
+    }
+
+    /// Moves on to the given picture, which keeps the page if it does not set one.
+    pub fn advance(&mut self, picture: &Pic){
+        self.picture = picture.nr.unwrap_or(self.picture + 1);
+        self.page = picture.page.unwrap_or(self.page);
+    }
+}
+
+impl std::fmt::Display for Location{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        write!(f, "volume {}, chapter ", self.volume)?;
+        if let Some(subchap) = self.subchapter{
+            write!(f, "{}", self.chapter as f32 + subchap)?;
+        } else {
+            write!(f, "{}", self.chapter)?;
+        }
+        write!(f, ", page {}, picture {}", self.page, self.picture)
+    }
+}
+
+/// A problem found in the data, with the text (counted from 1) it is about if any.
+#[derive(Debug, Clone)]
+pub struct Issue{
+    pub location: Location,
+    pub text: Option<usize>,
+    pub message: String,
+}
+
+pub fn write_issues(issues: &[Issue], doc: &mut String){
+    for issue in issues{
+        let _ = write!(doc, "{}", issue.location);
+        if let Some(text) = issue.text{
+            let _ = write!(doc, ", text {}", text);
+        }
+        let _ = writeln!(doc, ": {}", issue.message);
+    }
+    let _ = writeln!(doc, "{} issue(s) found.", issues.len());
+}
+
+/// How a report is written.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Format { Markdown, Html, Json, Csv, Text, Dot, Graphml }
+
+impl Format{
+    pub fn extension(&self) -> &'static str{
+        match self{
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Json => "json",
+            Self::Csv => "csv",
+            // the plain text reports have always been written to .md files
+            Self::Text => "md",
+            Self::Dot => "dot",
+            Self::Graphml => "graphml",
+        }
+    }
+}
+
+/// The numbers of a report, to be written in any format.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report{
+    #[serde(flatten)]
+    pub header: ReportHeader,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locations: Option<Vec<LocationRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scenes: Option<Vec<SceneRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transitions: Option<Vec<Transition>>,
+    pub rankings: Vec<Ranking>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prominence: Option<Composition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glossary: Option<Vec<GlossaryRow>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub periods: Vec<Period>,
+}
+
+impl Report{
+    pub fn new(mut header: ReportHeader) -> Self{
+        header.volumes.sort();
+        header.volumes.dedup();
+        header.chapters.sort();
+        header.chapters.dedup();
+        Self{
+            header,
+            locations: None,
+            scenes: None,
+            transitions: None,
+            rankings: Vec::new(),
+            prominence: None,
+            glossary: None,
+            periods: Vec::new(),
+        }
+    }
+}
 
-    h.volumes.sort();
-    h.volumes.dedup();
-    for vol in &h.volumes{
-        let _ = write!(doc, "{}, ", vol);
+/// A location with it's full path, listed after it's parent.
+#[derive(Debug, Clone, Serialize)]
+pub struct LocationRow{
+    pub location: String,
+    pub appearances: usize,
+    pub morae: usize,
+}
+
+/// The scenes at a location: runs of consecutive pictures there.
+#[derive(Debug, Clone, Serialize)]
+pub struct SceneRow{
+    pub location: String,
+    pub scenes: usize,
+    pub average_pictures: f64,
+    pub average_morae: f64,
+}
+
+/// How often a scene at one location was followed by a scene at another.
+#[derive(Debug, Clone, Serialize)]
+pub struct Transition{
+    pub from: String,
+    pub to: String,
+    pub count: usize,
+}
+
+/// How the prominence of the characters was scored: the weighted parts and what every part added
+/// to the score of every character, in percent.
+#[derive(Debug, Clone, Serialize)]
+pub struct Composition{
+    pub parts: Vec<Part>,
+    pub characters: Vec<ComposedScore>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Part{
+    pub name: &'static str,
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComposedScore{
+    pub name: String,
+    pub parts: Vec<f64>,
+    pub score: f64,
+}
+
+/// A sound effect with it's meaning and the pictures it is in.
+#[derive(Debug, Clone, Serialize)]
+pub struct GlossaryRow{
+    pub sound_effect: String,
+    pub romanized: String,
+    pub meaning: String,
+    pub frequency: usize,
+    pub pictures: Vec<String>,
+}
+
+/// The report of a single chapter or volume.
+#[derive(Debug, Clone, Serialize)]
+pub struct Period{
+    pub breakdown: &'static str,
+    pub period: String,
+    #[serde(flatten)]
+    pub report: Report,
+}
+
+/// A count, or a share in percent.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize)]
+#[serde(untagged)]
+pub enum Value{ Count(usize), Percentage(f64) }
+
+impl From<usize> for Value{
+    fn from(count: usize) -> Self{
+        Self::Count(count)
+    }
+}
+
+impl From<f64> for Value{
+    fn from(percentage: f64) -> Self{
+        Self::Percentage(percentage)
     }
-    doc.pop();
-    doc.pop();
-    let _ = writeln!(doc);
+}
 
-    let _ = write!(doc, "Chapters: ");
-    h.chapters.sort();
-    h.chapters.dedup();
-    for chap in &h.chapters{
-        let _ = write!(doc, "{}, ", chap);
+impl std::ops::Add for Value{
+    type Output = Self;
+    fn add(self, other: Self) -> Self{
+        match (self, other){
+            (Self::Count(a), Self::Count(b)) => Self::Count(a + b),
+            (a, b) => Self::Percentage(a.as_f64() + b.as_f64()),
+        }
     }
-    doc.pop();
-    doc.pop();
-    let _ = writeln!(doc);
+}
 
+impl Value{
+    fn as_f64(&self) -> f64{
+        match self{
+            Self::Count(count) => *count as f64,
+            Self::Percentage(percentage) => *percentage,
+        }
+    }
+}
+
+impl std::fmt::Display for Value{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        match self{
+            Self::Count(count) => write!(f, "{}", count),
+            Self::Percentage(percentage) => write!(f, "{:.2}%", percentage),
+        }
+    }
+}
+
+/// Names ranked on some value, the highest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct Ranking{
+    pub key: &'static str,
+    #[serde(skip)]
+    pub title: &'static str,
+    pub total: Value,
+    pub entries: Vec<Ranked>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ranked{
+    pub name: String,
+    pub value: Value,
+}
+
+pub fn ranking<T>(key: &'static str, title: &'static str, col: &HashMap<String, T>) -> Ranking
+    where T: Into<Value> + Copy + Default
+{
+    let total = col.values().fold(T::default().into(), |acc, x| acc + (*x).into());
+    let mut entries = col.iter()
+        .map(|(name, value)| Ranked{ name: name.clone(), value: (*value).into() })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| b.value.partial_cmp(&a.value).unwrap().then(a.name.cmp(&b.name)));
+    Ranking{ key, title, total, entries }
+}
+
+pub fn write_report(report: &Report, format: Format, doc: &mut String){
+    match format{
+        Format::Json => match serde_json::to_string_pretty(report){
+            Ok(json) => { let _ = writeln!(doc, "{}", json); },
+            Err(error) => { let _ = writeln!(doc, "Could not write json.\n\tError: {}", error); },
+        },
+        Format::Csv => {
+            let _ = writeln!(doc, "period,section,name,value");
+            write_csv(report, "", doc);
+        },
+        Format::Markdown => write_markdown(report, 2, doc),
+        _ => write_text(report, doc),
+    }
+}
+
+fn write_text(report: &Report, doc: &mut String){
+    let h = &report.header;
+    let join = |numbers: &[usize]| numbers.iter()
+        .map(|n| n.to_string()).collect::<Vec<_>>().join(", ");
+    let _ = writeln!(doc, "Manga: {}", h.manga);
+    let _ = writeln!(doc, "Volumes: {}", join(&h.volumes));
+    let _ = writeln!(doc, "Chapters: {}", join(&h.chapters));
     let _ = writeln!(doc, "Pictures: {}", h.pictures);
     let _ = writeln!(doc, "Morae spoken: {}", h.morae);
+    if let Some(locations) = &report.locations{
+        let _ = writeln!(doc, "Locations: ");
+        for row in locations{
+            let depth = row.location.matches('/').count() + 1;
+            let leaf = row.location.rsplit('/').next().unwrap_or(&row.location);
+            let _ = writeln!(
+                doc, "{}{}: {} appearances, {} morae spoken in.",
+                "\t".repeat(depth), leaf, row.appearances, row.morae
+            );
+        }
+    }
+    if let Some(scenes) = &report.scenes{
+        let total = scenes.iter().map(|row| row.scenes).sum::<usize>();
+        let _ = writeln!(doc, "Scenes: (out of {})", total);
+        for row in scenes{
+            let _ = writeln!(
+                doc, "\t{}: {} scenes, {:.2} pictures and {:.2} morae on average.",
+                row.location, row.scenes, row.average_pictures, row.average_morae
+            );
+        }
+    }
+    if let Some(transitions) = &report.transitions{
+        let _ = writeln!(doc, "Location transitions:");
+        for Transition{ from, to, count } in transitions{
+            let _ = writeln!(doc, "\t{} -> {}: {}", from, to, count);
+        }
+    }
+    for ranking in &report.rankings{
+        let _ = writeln!(doc, "{}: (out of {})", ranking.title, ranking.total);
+        for Ranked{ name, value } in &ranking.entries{
+            let _ = writeln!(doc, "\t{}: {}", name, value);
+        }
+    }
+    if let Some(composition) = &report.prominence{
+        let parts = composition.parts.iter()
+            .map(|part| format!("{} * {}", part.weight, part.name))
+            .collect::<Vec<_>>();
+        let _ = writeln!(doc, "Character prominence composition: {}", parts.join(" + "));
+        for c in &composition.characters{
+            let parts = c.parts.iter().zip(&composition.parts)
+                .map(|(value, part)| format!("{:.2}% {}", value, part.name))
+                .collect::<Vec<_>>();
+            let _ = writeln!(doc, "\t{}: {} = {:.2}%", c.name, parts.join(" + "), c.score);
+        }
+    }
+    if let Some(glossary) = &report.glossary{
+        let total = glossary.iter().map(|row| row.frequency).sum::<usize>();
+        let _ = writeln!(doc, "Sound effects: (out of {})", total);
+        for row in glossary{
+            let _ = writeln!(
+                doc, "\t{} ({}): {}, {} time(s), in {}.",
+                row.sound_effect, row.romanized, row.meaning, row.frequency,
+                row.pictures.join("; ")
+            );
+        }
+    }
+    for period in &report.periods{
+        let _ = writeln!(doc, "\n## {}\n", period_title(period));
+        write_text(&period.report, doc);
+    }
 }
 
-pub fn write_list<'a,T>(col: &'a HashMap<String, T>, title: &str, unit: &str, doc: &mut String) -> T
-    where T: std::fmt::Display + PartialOrd + Copy + std::iter::Sum<&'a T> + 'a
-{
-    let total: T = col.iter().map(|(_, c)| c).sum();
-    let _ = writeln!(doc, "{} (out of {:.2}{})", title, total, unit);
-    let mut list = col.iter().collect::<Vec<_>>();
-    list.sort_unstable_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap());
-    for (name, count) in list{
-        let _ = writeln!(doc, "\t{}: {:.2}{}", name, count, unit);
+fn write_markdown(report: &Report, rank: usize, doc: &mut String){
+    let h = &report.header;
+    let header = "#".repeat(rank.clamp(1, 6));
+    let join = |numbers: &[usize]| numbers.iter()
+        .map(|n| n.to_string()).collect::<Vec<_>>().join(", ");
+    let _ = writeln!(doc, "- Manga: {}", h.manga);
+    let _ = writeln!(doc, "- Volumes: {}", join(&h.volumes));
+    let _ = writeln!(doc, "- Chapters: {}", join(&h.chapters));
+    let _ = writeln!(doc, "- Pictures: {}", h.pictures);
+    let _ = writeln!(doc, "- Morae spoken: {}", h.morae);
+    if let Some(locations) = &report.locations{
+        let _ = writeln!(doc, "\n{} Locations\n", header);
+        let _ = writeln!(doc, "| location | appearances | morae spoken in |");
+        let _ = writeln!(doc, "|---|---:|---:|");
+        for row in locations{
+            let _ = writeln!(doc, "| {} | {} | {} |", row.location, row.appearances, row.morae);
+        }
+    }
+    if let Some(scenes) = &report.scenes{
+        let _ = writeln!(doc, "\n{} Scenes\n", header);
+        let _ = writeln!(doc, "| location | scenes | average pictures | average morae |");
+        let _ = writeln!(doc, "|---|---:|---:|---:|");
+        for row in scenes{
+            let _ = writeln!(
+                doc, "| {} | {} | {:.2} | {:.2} |",
+                row.location, row.scenes, row.average_pictures, row.average_morae
+            );
+        }
+    }
+    if let Some(transitions) = &report.transitions{
+        // a matrix with the location a transition is from as row and where it goes as column
+        let mut froms = transitions.iter().map(|t| t.from.as_str()).collect::<Vec<_>>();
+        let mut tos = transitions.iter().map(|t| t.to.as_str()).collect::<Vec<_>>();
+        froms.sort();
+        froms.dedup();
+        tos.sort();
+        tos.dedup();
+        let _ = writeln!(doc, "\n{} Location transitions\n", header);
+        let _ = writeln!(doc, "| from \\ to | {} |", tos.join(" | "));
+        let _ = writeln!(doc, "|---|{}", "---:|".repeat(tos.len()));
+        for from in froms{
+            let cells = tos.iter().map(|to| transitions.iter()
+                .find(|t| t.from == from && t.to == *to)
+                .map_or(0, |t| t.count)
+                .to_string()
+            ).collect::<Vec<_>>();
+            let _ = writeln!(doc, "| {} | {} |", from, cells.join(" | "));
+        }
+    }
+    for ranking in &report.rankings{
+        let _ = writeln!(doc, "\n{} {} (out of {})\n", header, ranking.title, ranking.total);
+        let _ = writeln!(doc, "| name | value |");
+        let _ = writeln!(doc, "|---|---:|");
+        for Ranked{ name, value } in &ranking.entries{
+            let _ = writeln!(doc, "| {} | {} |", name, value);
+        }
+    }
+    if let Some(composition) = &report.prominence{
+        let _ = writeln!(doc, "\n{} Character prominence composition\n", header);
+        let parts = composition.parts.iter()
+            .map(|part| format!("{} * {}", part.weight, part.name))
+            .collect::<Vec<_>>();
+        let _ = writeln!(doc, "| name | {} | score |", parts.join(" | "));
+        let _ = writeln!(doc, "|---|{}---:|", "---:|".repeat(parts.len()));
+        for c in &composition.characters{
+            let parts = c.parts.iter().map(|value| format!("{:.2}%", value)).collect::<Vec<_>>();
+            let _ = writeln!(doc, "| {} | {} | {:.2}% |", c.name, parts.join(" | "), c.score);
+        }
+    }
+    if let Some(glossary) = &report.glossary{
+        let cell = |string: &str| string.replace('|', "\\|");
+        let _ = writeln!(doc, "\n{} Sound effects\n", header);
+        let _ = writeln!(doc, "| sound effect | romanized | meaning | frequency | pictures |");
+        let _ = writeln!(doc, "|---|---|---|---:|---|");
+        for row in glossary{
+            let _ = writeln!(
+                doc, "| {} | {} | {} | {} | {} |",
+                cell(&row.sound_effect), cell(&row.romanized), cell(&row.meaning), row.frequency,
+                row.pictures.join("; ")
+            );
+        }
+    }
+    for period in &report.periods{
+        let _ = writeln!(doc, "\n{} {}\n", header, period_title(period));
+        write_markdown(&period.report, rank + 1, doc);
+    }
+}
+
+fn write_csv(report: &Report, period: &str, doc: &mut String){
+    let h = &report.header;
+    let join = |numbers: &[usize]| numbers.iter()
+        .map(|n| n.to_string()).collect::<Vec<_>>().join(" ");
+    let mut row = |section: &str, name: &str, value: &str| {
+        let _ = writeln!(
+            doc, "{},{},{},{}", csv_field(period), section, csv_field(name), csv_field(value)
+        );
+    };
+    row("header", "manga", &h.manga);
+    row("header", "volumes", &join(&h.volumes));
+    row("header", "chapters", &join(&h.chapters));
+    row("header", "pictures", &h.pictures.to_string());
+    row("header", "morae", &h.morae.to_string());
+    for location in report.locations.iter().flatten(){
+        row("location_appearances", &location.location, &location.appearances.to_string());
+        row("location_morae", &location.location, &location.morae.to_string());
+    }
+    for scene in report.scenes.iter().flatten(){
+        row("scenes", &scene.location, &scene.scenes.to_string());
+        row("scene_average_pictures", &scene.location, &format!("{:.2}", scene.average_pictures));
+        row("scene_average_morae", &scene.location, &format!("{:.2}", scene.average_morae));
+    }
+    for Transition{ from, to, count } in report.transitions.iter().flatten(){
+        row("location_transitions", &format!("{} -> {}", from, to), &count.to_string());
+    }
+    for ranking in &report.rankings{
+        for Ranked{ name, value } in &ranking.entries{
+            let value = match value{
+                Value::Count(count) => count.to_string(),
+                Value::Percentage(percentage) => format!("{:.2}", percentage),
+            };
+            row(ranking.key, name, &value);
+        }
+    }
+    if let Some(composition) = &report.prominence{
+        for c in &composition.characters{
+            for (value, part) in c.parts.iter().zip(&composition.parts){
+                let section = format!("prominence_{}", part.name.replace(' ', "_"));
+                row(&section, &c.name, &format!("{:.2}", value));
+            }
+        }
+    }
+    for g in report.glossary.iter().flatten(){
+        row("sfx_romanized", &g.sound_effect, &g.romanized);
+        row("sfx_meaning", &g.sound_effect, &g.meaning);
+        row("sfx_frequency", &g.sound_effect, &g.frequency.to_string());
+        row("sfx_pictures", &g.sound_effect, &g.pictures.join("; "));
+    }
+    for p in &report.periods{
+        write_csv(&p.report, &format!("{} {}", p.breakdown, p.period), doc);
     }
-    total
 }
 
+fn period_title(period: &Period) -> String{
+    let mut chars = period.breakdown.chars();
+    let first = chars.next().map(|c| c.to_uppercase().to_string()).unwrap_or_default();
+    format!("{}{} {}", first, chars.as_str(), period.period)
+}
+
+pub fn csv_field(field: &str) -> String{
+    if field.contains([',', '"', '\n']){
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
 
 pub fn update<T: Copy + Default + Sized, F>(map: &mut HashMap<String, T>, key: &str, fun: F)
     where F: Fn(T) -> T
@@ -100,3 +557,31 @@ pub fn log_todo(text: &Text, log: &mut String){
         );
     }
 }
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_report(){
+        let col = HashMap::from([
+            ("b".to_string(), 2), ("a".to_string(), 2), ("c, d".to_string(), 5)
+        ]);
+        let mut report = Report::new(ReportHeader{
+            manga: "m".to_string(), volumes: vec![2, 1, 2], ..Default::default()
+        });
+        report.rankings.push(ranking("key", "Title", &col));
+        let mut doc = String::new();
+        write_report(&report, Format::Text, &mut doc);
+        assert_eq!(doc, concat!(
+            "Manga: m\nVolumes: 1, 2\nChapters: \nPictures: 0\nMorae spoken: 0\n",
+            "Title: (out of 9)\n\tc, d: 5\n\ta: 2\n\tb: 2\n",
+        ));
+        doc.clear();
+        write_report(&report, Format::Csv, &mut doc);
+        assert!(doc.contains(",header,volumes,1 2\n"));
+        assert!(doc.ends_with(",key,\"c, d\",5\n,key,a,2\n,key,b,2\n"));
+        let prominence = ranking("p", "P", &HashMap::from([("a".to_string(), 12.345)]));
+        assert_eq!(prominence.entries[0].value.to_string(), "12.35%");
+    }
+}
@@ -0,0 +1,84 @@
+use crate::structure::*;
+use crate::characters::*;
+use crate::config::*;
+
+use serde::Deserialize;
+
+use std::fs;
+use std::fmt::Write;
+use std::path::{ Path, PathBuf };
+use std::collections::HashMap;
+
+pub const SERIES_FILE: &str = "series.toml";
+
+/// Information shared by all chapters of a manga.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Series{
+    pub manga: Option<String>,
+    pub author: Option<String>,
+    pub romanized: Option<String>,
+    #[serde(default, rename = "character")]
+    pub characters: Vec<Character>,
+    #[serde(default)]
+    pub locations: HashMap<String, String>,
+    #[serde(default)]
+    pub sfx: HashMap<String, String>,
+    pub config: Option<Config>,
+}
+
+/// Loads the series file given with `--series`, or else the `series.toml` next to the first input
+/// file, if there is one.
+pub fn get_series(explicit: Option<&PathBuf>, first_input: &Path) -> Result<Option<Series>, String>{
+    let file = if let Some(file) = explicit{
+        file.clone()
+    } else {
+        let beside = first_input.with_file_name(SERIES_FILE);
+        if !beside.is_file() { return Ok(None); }
+        beside
+    };
+    let contents = fs::read_to_string(&file).map_err(|error|
+        format!("Could not read series file: \"{}\".\n\tError: {}", file.display(), error)
+    )?;
+    toml::from_str::<Series>(&contents).map(Some).map_err(|error|
+        format!("Could not parse series file: \"{}\".\n\tError: {}", file.display(), error)
+    )
+}
+
+/// Adds the characters, locations and sound effects of the series to the config.
+/// What is declared in the config takes precedence.
+pub fn merge_series(series: &mut Series, conf: &mut Config){
+    conf.characters.extend(std::mem::take(&mut series.characters));
+    for (location, parent) in std::mem::take(&mut series.locations){
+        conf.locations.entry(location).or_insert(parent);
+    }
+    for (sfx, meaning) in std::mem::take(&mut series.sfx){
+        conf.sfx.entry(sfx).or_insert(meaning);
+    }
+}
+
+/// Fills in what the chapter leaves to the series.
+pub fn inherit_series(chapter: &mut Chapter, series: &Series, log: &mut String){
+    fn inherit(
+        field: &mut String, from: &Option<String>, name: &str, chapter: &str, log: &mut String
+    ){
+        match from{
+            Some(from) if field.is_empty() => *field = from.clone(),
+            Some(from) if field != from => {
+                let _ = writeln!(
+                    log, "Warning: chapter {} has {} \"{}\" but the series has \"{}\".",
+                    chapter, name, field, from
+                );
+            },
+            None if field.is_empty() => {
+                let _ = writeln!(
+                    log, "Warning: chapter {} has no {} and neither has the series.", chapter, name
+                );
+            },
+            _ => {},
+        }
+    }
+    let name = format!("{} of volume {}", chapter.chapter, chapter.volume);
+    inherit(&mut chapter.manga, &series.manga, "manga", &name, log);
+    inherit(&mut chapter.author, &series.author, "author", &name, log);
+}
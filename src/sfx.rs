@@ -0,0 +1,134 @@
+use crate::structure::*;
+use crate::japanese::*;
+use crate::report::*;
+use crate::config::*;
+
+use std::collections::HashMap;
+
+/// A sound effect with where it was found.
+#[derive(Debug, Clone, Default)]
+struct SoundEffect{
+    reading: String,
+    count: usize,
+    translation: Option<String>,
+    pictures: Vec<Location>,
+}
+
+/// Every sound effect of the chapters, by how it is written.
+#[derive(Debug, Clone, Default)]
+pub struct Glossary{
+    rp: ReportHeader,
+    effects: HashMap<String, SoundEffect>,
+}
+
+pub fn accumulate_glossary(chapter: &Chapter, glossary: &mut Glossary, log: &mut String){
+    set_current_manga(&mut glossary.rp.manga, chapter.manga.clone(), log);
+    glossary.rp.volumes.push(chapter.volume);
+    glossary.rp.chapters.push(chapter.chapter);
+    let mut location = Location::new(chapter);
+    for picture in &chapter.pic{
+        glossary.rp.pictures += 1;
+        location.advance(picture);
+        for text in picture.text.iter().flatten(){
+            let lines = text.lines.clone().vectorize();
+            let readings = map_kanjis(&lines, &text.kmap.clone().vectorize());
+            if text.kind != TextKind::Sfx{
+                glossary.rp.morae += readings.iter().flat_map(|line| line.chars())
+                    .map(to_mora).sum::<usize>();
+                continue;
+            }
+            log_todo(text, log);
+            let transl = text.transl.clone().vectorize();
+            for (i, (line, reading)) in lines.iter().zip(readings).enumerate(){
+                let sfx = line.replace([' ', PARTICLE_ESCAPE], "");
+                if sfx.is_empty() { continue; }
+                // the reading and translation are the first ones given
+                let effect = glossary.effects.entry(sfx).or_insert_with(|| SoundEffect{
+                    reading: reading.replace([' ', PARTICLE_ESCAPE], ""),
+                    ..Default::default()
+                });
+                effect.count += 1;
+                // the translation of the line, or of the whole text if it has a single one
+                let translation = transl.get(i).filter(|_| transl.len() == lines.len())
+                    .or(transl.first().filter(|_| transl.len() == 1));
+                if effect.translation.is_none(){
+                    effect.translation = translation.cloned();
+                }
+                if effect.pictures.last() != Some(&location){
+                    effect.pictures.push(location);
+                }
+            }
+        }
+    }
+}
+
+/// The sound effects, the most frequent first. The meaning is taken from the `sfx` table of the
+/// config, or else from the first translation given.
+pub fn glossary_report(glossary: &Glossary, conf: &Config) -> Report{
+    let mut report = Report::new(glossary.rp.clone());
+    let mut effects = glossary.effects.iter().collect::<Vec<_>>();
+    effects.sort_by(|(a, x), (b, y)| y.count.cmp(&x.count).then(a.cmp(b)));
+    report.glossary = Some(effects.into_iter().map(|(sfx, effect)| {
+        let romanized = if could_contain_kanji(std::slice::from_ref(&effect.reading)){
+            String::new()
+        } else {
+            romanize_with(&effect.reading, &conf.romanization)
+        };
+        let meaning = conf.sfx.get(sfx).or(conf.sfx.get(&effect.reading))
+            .or(effect.translation.as_ref())
+            .cloned()
+            .unwrap_or_default();
+        GlossaryRow{
+            sound_effect: sfx.clone(),
+            romanized,
+            meaning,
+            frequency: effect.count,
+            pictures: effect.pictures.iter().map(|location| location.to_string()).collect(),
+        }
+    }).collect());
+    report
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_glossary(){
+        let chapter = toml::from_str::<Chapter>(r#"
+            title = "t"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            [[pic.text]]
+            kind = "sfx"
+            lines = ["ドキドキ", "轟"]
+            kmap = ["轟", "ゴゴゴ"]
+            [[pic]]
+            [[pic.text]]
+            kind = "sfx"
+            lines = ["ドキドキ", "ガラッ", "轟"]
+            kmap = ["轟", "ドドド"]
+            transl = ["ba-dump", "slam", "rumble"]
+            [[pic.text]]
+            from = "a"
+            lines = "あいう"
+        "#).unwrap();
+        let mut conf = Config::default();
+        conf.sfx.insert("ガラッ".to_string(), "a door slides open".to_string());
+        let mut glossary = Glossary::default();
+        accumulate_glossary(&chapter, &mut glossary, &mut String::new());
+        let report = glossary_report(&glossary, &conf);
+        assert_eq!(report.header.morae, 3);
+        let rows = report.glossary.unwrap().into_iter()
+            .map(|row| (row.sound_effect, row.romanized, row.meaning, row.frequency))
+            .collect::<Vec<_>>();
+        let row = |sfx: &str, romanized: &str, meaning: &str, frequency: usize|
+            (sfx.to_string(), romanized.to_string(), meaning.to_string(), frequency);
+        assert_eq!(rows, vec![
+            row("ドキドキ", "dokidoki", "ba-dump", 2),
+            row("轟", "gogogo", "rumble", 2),
+            row("ガラッ", "garah", "a door slides open", 1),
+        ]);
+    }
+}
@@ -1,9 +1,14 @@
 use crate::structure::*;
 use crate::japanese::*;
 use crate::report::*;
+use crate::config::*;
+use crate::characters::*;
+use crate::graph::*;
+
+use serde::Deserialize;
 
 use std::fmt::Write;
-use std::collections::HashMap;
+use std::collections::{ HashMap, HashSet };
 
 #[derive(Debug, Clone, Default)]
 pub struct Stats{
@@ -11,40 +16,190 @@ pub struct Stats{
     locations: HashMap<String, (usize, usize)>,
     characters: HashMap<String, usize>,
     speaks: HashMap<String, usize>,
+    thinks: HashMap<String, usize>,
     spoken_to: HashMap<String, usize>,
-    conversation_pair: HashMap<String, usize>,
-    conversation_prominence: HashMap<String, usize>
+    conversation_pair: HashMap<(String, String), usize>,
+    conversations: HashMap<(String, String), usize>,
+    scenes: HashMap<String, (usize, usize, usize)>,
+    transitions: HashMap<(String, String), usize>,
+    unknown_characters: HashSet<String>,
+}
+
+/// How much every part counts towards the prominence of a character. Every part is the share a
+/// character has of the total, where morae spoken and morae spoken to share the total of both.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct Prominence{
+    pub appearances: f64,
+    pub morae_spoken: f64,
+    pub morae_spoken_to: f64,
+    /// The number of different characters a character talks with.
+    pub partners: f64,
+}
+
+impl Default for Prominence{
+    fn default() -> Self{
+        Self{
+            appearances: 1.0,
+            morae_spoken: 1.0,
+            morae_spoken_to: 1.0,
+            partners: 0.0,
+        }
+    }
 }
 
-pub fn stats_report(mut s: Stats, doc: &mut String){
-    write_header(&mut s.rp, doc);
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Breakdown { #[default] Chapter, Volume }
 
-    let _ = writeln!(doc, "Locations: ");
-    let mut locs = s.locations.into_iter().collect::<Vec<_>>();
-    locs.sort_unstable_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap());
-    for (name, (count, morae)) in locs{
-        let _ = writeln!(doc, "\t{}: {} appearances, {} morae spoken in.", name, count, morae);
+impl Breakdown{
+    fn name(&self) -> &'static str{
+        match self{
+            Self::Chapter => "chapter",
+            Self::Volume => "volume",
+        }
     }
 
-    let total_appearances = write_list(&s.characters, "Character appearances:", "", doc) as f64;
-    write_list(&s.speaks, "Morae spoken:", "", doc);
-    write_list(&s.spoken_to, "Morae spoken to:", "", doc);
-    write_list(&s.conversation_pair, "Conversation pairs in morae:", "", doc);
+    fn period(&self, chapter: &Chapter) -> String{
+        match (self, chapter.subchapter){
+            (Self::Chapter, Some(subchap)) => (chapter.chapter as f32 + subchap).to_string(),
+            (Self::Chapter, None) => chapter.chapter.to_string(),
+            (Self::Volume, _) => chapter.volume.to_string(),
+        }
+    }
+}
 
-    let total_prom: usize = s.conversation_prominence.values().sum();
-    let mut prom = s.conversation_prominence.into_iter()
-        .map(|(s, c)| (s, c as f64 / total_prom as f64))
-        .collect::<HashMap<_, f64>>();
+/// The stats of every chapter or volume on their own, in the order they were read.
+#[derive(Debug, Clone, Default)]
+pub struct Timeline{
+    breakdown: Breakdown,
+    periods: Vec<(String, Stats)>,
+}
 
-    for (character, count) in &s.characters{
-        let val = *count as f64 / total_appearances;
-        update(&mut prom, character, |x| (x + val));
+impl Timeline{
+    pub fn new(breakdown: Breakdown) -> Self{
+        Self{ breakdown, periods: Vec::new() }
     }
-    prom.iter_mut().for_each(|(_, c)| *c *= 50.0);
-    write_list(&prom, "Character prominence:", "%", doc);
 }
 
-pub fn accumulate_stats(chapter: Chapter, stats: &mut Stats, log: &mut String){
+pub fn stats_report(s: Stats, conf: &Config) -> Report{
+    let mut report = Report::new(s.rp);
+    let reg = &conf.characters;
+    // non-diegetic characters are left out of the rankings, but not out of their totals
+    let excluded = s.characters.keys().chain(s.speaks.keys()).chain(s.spoken_to.keys())
+        .chain(s.thinks.keys())
+        .filter(|id| conf.is_non_diegetic(id))
+        .cloned()
+        .collect::<HashSet<_>>();
+    let shown = |map: &HashMap<String, usize>| map.iter()
+        .filter(|(id, _)| !excluded.contains(*id))
+        .map(|(id, c)| (reg.display(id), *c))
+        .collect::<HashMap<_, _>>();
+    let mut partners = HashMap::new();
+    for (a, b) in s.conversation_pair.keys().filter(|(a, b)| a != b){
+        if excluded.contains(a) || excluded.contains(b) { continue; }
+        update(&mut partners, a, |x| x + 1);
+        update(&mut partners, b, |x| x + 1);
+    }
+    let conversation_pair = s.conversation_pair.iter()
+        .filter(|((a, b), _)| !excluded.contains(a) && !excluded.contains(b))
+        .map(|((a, b), c)| (format!("{}, {}", reg.display(a), reg.display(b)), *c))
+        .collect::<HashMap<_, _>>();
+
+    let mut locations = Vec::new();
+    location_rows(&s.locations, None, &mut locations);
+    report.locations = Some(locations);
+
+    let mut scenes = s.scenes.iter().map(|(location, (scenes, pictures, morae))| SceneRow{
+        location: location.clone(),
+        scenes: *scenes,
+        average_pictures: *pictures as f64 / *scenes as f64,
+        average_morae: *morae as f64 / *scenes as f64,
+    }).collect::<Vec<_>>();
+    scenes.sort_by(|a, b| b.scenes.cmp(&a.scenes).then(a.location.cmp(&b.location)));
+    report.scenes = Some(scenes);
+    let mut transitions = s.transitions.iter().map(|((from, to), count)| Transition{
+        from: from.clone(),
+        to: to.clone(),
+        count: *count,
+    }).collect::<Vec<_>>();
+    transitions.sort_by(|a, b|
+        b.count.cmp(&a.count).then(a.from.cmp(&b.from)).then(a.to.cmp(&b.to))
+    );
+    report.transitions = Some(transitions);
+
+    fn total<K>(col: &HashMap<K, usize>) -> usize{
+        col.values().sum()
+    }
+    let rankings = [
+        ("character_appearances", "Character appearances", &s.characters, total(&s.characters)),
+        ("morae_spoken", "Morae spoken", &s.speaks, total(&s.speaks)),
+        ("morae_spoken_to", "Morae spoken to", &s.spoken_to, total(&s.spoken_to)),
+        ("morae_thought", "Morae thought", &s.thinks, total(&s.thinks)),
+    ].map(|(key, title, col, total)| (key, title, shown(col), total));
+    let pairs = (
+        "conversation_pairs", "Conversation pairs in morae", conversation_pair,
+        total(&s.conversation_pair)
+    );
+    for (key, title, col, total) in rankings.into_iter().chain([pairs]){
+        let mut ranking = ranking(key, title, &col);
+        ranking.total = total.into();
+        report.rankings.push(ranking);
+    }
+
+    let mut composition = prominence(
+        &conf.prominence, &s.characters, &s.speaks, &s.spoken_to, &partners, &excluded
+    );
+    composition.characters.iter_mut().for_each(|c| c.name = reg.display(&c.name));
+    let prom = composition.characters.iter()
+        .map(|c| (c.name.clone(), c.score))
+        .collect::<HashMap<_, _>>();
+    report.rankings.push(ranking("character_prominence", "Character prominence", &prom));
+    report.prominence = Some(composition);
+    report
+}
+
+/// Scores every character on the weighted parts, scaled so that all scores add up to 100%.
+/// The excluded characters are not scored, but still count towards the totals of the parts.
+fn prominence(
+    weights: &Prominence, appearances: &HashMap<String, usize>, speaks: &HashMap<String, usize>,
+    spoken_to: &HashMap<String, usize>, partners: &HashMap<String, usize>,
+    excluded: &HashSet<String>
+) -> Composition{
+    let sum = |map: &HashMap<String, usize>| map.values().sum::<usize>() as f64;
+    let conversation = sum(speaks) + sum(spoken_to);
+    let parts = [
+        ("appearances", weights.appearances, appearances, sum(appearances)),
+        ("morae spoken", weights.morae_spoken, speaks, conversation),
+        ("morae spoken to", weights.morae_spoken_to, spoken_to, conversation),
+        ("partners", weights.partners, partners, sum(partners)),
+    ];
+    let parts = parts.into_iter().filter(|(_, weight, _, _)| *weight != 0.0).collect::<Vec<_>>();
+    let mut names = parts.iter().flat_map(|(_, _, map, _)| map.keys())
+        .filter(|name| !excluded.contains(*name))
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    let mut characters = names.into_iter().map(|name| {
+        let parts = parts.iter().map(|(_, weight, map, total)| {
+            let count = map.get(name).copied().unwrap_or(0) as f64;
+            if *total > 0.0 { weight * count / total } else { 0.0 }
+        }).collect::<Vec<_>>();
+        ComposedScore{ name: name.clone(), score: parts.iter().sum(), parts }
+    }).collect::<Vec<_>>();
+    let total = characters.iter().map(|c| c.score).sum::<f64>();
+    let scale = if total > 0.0 { 100.0 / total } else { 0.0 };
+    for c in &mut characters{
+        c.parts.iter_mut().for_each(|part| *part *= scale);
+        c.score *= scale;
+    }
+    characters.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap().then(a.name.cmp(&b.name)));
+    Composition{
+        parts: parts.iter().map(|(name, weight, _, _)| Part{ name, weight: *weight }).collect(),
+        characters,
+    }
+}
+
+pub fn accumulate_stats(chapter: Chapter, conf: &Config, stats: &mut Stats, log: &mut String){
     set_current_manga(&mut stats.rp.manga, chapter.manga.clone(), log);
     stats.rp.volumes.push(chapter.volume);
     stats.rp.chapters.push(chapter.chapter);
@@ -53,16 +208,23 @@ pub fn accumulate_stats(chapter: Chapter, stats: &mut Stats, log: &mut String){
     chapter_header_log(&chapter, log);
 
     let mut last_location = String::from("");
+    // location, pictures and morae of the current scene, the chapter starts without one, so there
+    // are no transitions from the last location of the previous chapter
+    let mut scene: Option<(String, usize, usize)> = None;
 
     for picture in chapter.pic{
         stats.rp.pictures += 1;
         let location = picture.location.unwrap_or(last_location);
         let mut pic_morae = 0;
         for character in picture.characters.vectorize(){
+            let character = resolve_character(
+                &conf.characters, character, &mut stats.unknown_characters, log
+            );
             update(&mut stats.characters, &character, |x| x + 1);
         }
         if let Some(texts) = picture.text{
-            for text in texts{
+            // sound effects are not language that is spoken
+            for text in texts.into_iter().filter(|text| text.kind != TextKind::Sfx){
                 log_todo(&text, log);
                 let lines = text.lines.vectorize();
                 let replacements = if let Some(kmap) = text.kmap{
@@ -83,31 +245,516 @@ pub fn accumulate_stats(chapter: Chapter, stats: &mut Stats, log: &mut String){
                 stats.rp.morae += morae;
                 pic_morae += morae;
 
-                let froms = text.from.vectorize();
+                let froms = text.from.vectorize().into_iter()
+                    .map(|name| resolve_character(
+                        &conf.characters, name, &mut stats.unknown_characters, log
+                    ))
+                    .collect::<Vec<_>>();
+                if text.kind.has_speaker() && froms.is_empty(){
+                    let _ = writeln!(
+                        log,
+                        "Warning: {} text has no \"from\" with the lines:\n{:#?}",
+                        text.kind.name(),
+                        lines
+                    );
+                }
                 let froms = froms.iter();
-                let tos = text.to.vectorize();
+                let tos = text.to.vectorize().into_iter()
+                    .map(|name| resolve_character(
+                        &conf.characters, name, &mut stats.unknown_characters, log
+                    ))
+                    .collect::<Vec<_>>();
                 let tos = tos.iter();
 
+                match text.kind{
+                    TextKind::Thought => {
+                        froms.for_each(|f| update(&mut stats.thinks, f, |x| x + morae));
+                        continue;
+                    },
+                    TextKind::Sign => continue,
+                    _ => {},
+                }
                 froms.clone().for_each(|f| update(&mut stats.speaks, f, |x| x + morae));
-                froms.clone().for_each(
-                    |f| update(&mut stats.conversation_prominence, f, |x| x + morae)
-                );
                 for receiver in tos.clone(){
                     update(&mut stats.spoken_to, receiver, |x| x + morae);
-                    update(&mut stats.conversation_prominence, receiver, |x| x + morae);
                     for speaker in froms.clone(){
                         let pair = if speaker.cmp(receiver) == std::cmp::Ordering::Less{
-                            format!("{}, {}", speaker, receiver)
+                            (speaker.clone(), receiver.clone())
                         } else {
-                            format!("{}, {}", receiver, speaker)
+                            (receiver.clone(), speaker.clone())
                         };
-                        update(&mut stats.conversation_pair, &pair, |x| x + morae);
+                        *stats.conversation_pair.entry(pair).or_default() += morae;
+                        *stats.conversations.entry((speaker.clone(), receiver.clone()))
+                            .or_default() += morae;
                     }
                 }
             }
         };
-        update(&mut stats.locations, &location, |(a, b)| (a + 1, b + pic_morae));
+        let ancestry = location_ancestry(&location, &conf.locations);
+        for loc in &ancestry{
+            update(&mut stats.locations, loc, |(a, b)| (a + 1, b + pic_morae));
+        }
+        // the full path, so a location is the same place however much of the path is written
+        let place = ancestry.last().cloned().unwrap_or_default();
+        if scene.as_ref().is_none_or(|(current, _, _)| *current != place){
+            if let Some((previous, pictures, morae)) = scene.take(){
+                end_scene(stats, &previous, pictures, morae);
+                if !previous.is_empty() && !place.is_empty(){
+                    *stats.transitions.entry((previous, place.clone())).or_default() += 1;
+                }
+            }
+            scene = Some((place, 0, 0));
+        }
+        if let Some((_, pictures, morae)) = &mut scene{
+            *pictures += 1;
+            *morae += pic_morae;
+        }
         last_location = location;
     }
+    if let Some((location, pictures, morae)) = scene{
+        end_scene(stats, &location, pictures, morae);
+    }
+}
+
+// A scene is a run of pictures at the same location, it ends with the chapter.
+fn end_scene(stats: &mut Stats, location: &str, pictures: usize, morae: usize){
+    if location.is_empty() { return; }
+    let (scenes, total_pictures, total_morae) = stats.scenes.entry(location.to_string())
+        .or_default();
+    *scenes += 1;
+    *total_pictures += pictures;
+    *total_morae += morae;
+}
+
+/// The characters as nodes, weighted by their appearances or the morae they speak, and who speaks
+/// to who as directed edges, weighted by morae.
+pub fn interaction_graph(s: &Stats, conf: &Config, weight: NodeWeight) -> Graph{
+    let weights = match weight{
+        NodeWeight::Appearances => &s.characters,
+        NodeWeight::Morae => &s.speaks,
+    };
+    let mut ids = s.characters.keys().chain(s.speaks.keys()).chain(s.spoken_to.keys())
+        .filter(|id| !conf.is_hidden(id))
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    let mut nodes = ids.into_iter().map(|id| Node{
+        id: id.clone(),
+        label: conf.characters.display(id),
+        weight: weights.get(id).copied().unwrap_or(0),
+    }).collect::<Vec<_>>();
+    nodes.sort_by(|a, b| b.weight.cmp(&a.weight).then(a.id.cmp(&b.id)));
+    let mut edges = s.conversations.iter()
+        .filter(|((from, to), _)| !conf.is_hidden(from) && !conf.is_hidden(to))
+        .map(|((from, to), morae)| Edge{ from: from.clone(), to: to.clone(), morae: *morae })
+        .collect::<Vec<_>>();
+    edges.sort_by(|a, b| b.morae.cmp(&a.morae).then(a.from.cmp(&b.from)).then(a.to.cmp(&b.to)));
+    Graph{ nodes, edges }
+}
+
+/// Adds the chapter to the stats of it's period. The chapters have to come in order.
+/// Nothing is logged, as the chapter is accumulated in the overall stats as well.
+pub fn accumulate_timeline(chapter: &Chapter, conf: &Config, timeline: &mut Timeline){
+    let period = timeline.breakdown.period(chapter);
+    if timeline.periods.last().map(|(last, _)| last) != Some(&period){
+        timeline.periods.push((period, Stats::default()));
+    }
+    if let Some((_, stats)) = timeline.periods.last_mut(){
+        accumulate_stats(chapter.clone(), conf, stats, &mut String::new());
+    }
+}
+
+/// The report of every period.
+pub fn timeline_periods(timeline: &Timeline, conf: &Config) -> Vec<Period>{
+    timeline.periods.iter().map(|(period, stats)| Period{
+        breakdown: timeline.breakdown.name(),
+        period: period.clone(),
+        report: stats_report(stats.clone(), conf),
+    }).collect()
 }
 
+/// Writes timelines of the appearances and morae spoken of every character as Markdown tables.
+pub fn timeline_tables(timeline: &Timeline, conf: &Config, doc: &mut String){
+    let name = timeline.breakdown.name();
+    let characters = timeline_characters(timeline, conf);
+    let periods = timeline.periods.iter()
+        .map(|(period, _)| format!("{} {}", name, period))
+        .collect::<Vec<_>>();
+    for (title, column) in [("appearances", 0), ("morae spoken", 1)]{
+        let _ = writeln!(doc, "\nTimeline of character {}:\n", title);
+        let _ = writeln!(doc, "| character | {} |", periods.join(" | "));
+        let _ = writeln!(doc, "|---|{}", "---:|".repeat(periods.len()));
+        for character in &characters{
+            let cells = timeline.periods.iter()
+                .map(|(_, stats)| timeline_cell(stats, character)[column].to_string())
+                .collect::<Vec<_>>();
+            let _ = writeln!(
+                doc, "| {} | {} |", conf.characters.display(character), cells.join(" | ")
+            );
+        }
+    }
+}
+
+/// Writes the timeline as CSV, with a row for every character in every period.
+pub fn timeline_csv(timeline: &Timeline, conf: &Config, csv: &mut String){
+    let _ = writeln!(csv, "{},character,appearances,morae spoken", timeline.breakdown.name());
+    let characters = timeline_characters(timeline, conf);
+    for (period, stats) in &timeline.periods{
+        for character in &characters{
+            let [appearances, morae] = timeline_cell(stats, character);
+            let _ = writeln!(
+                csv, "{},{},{},{}",
+                period, csv_field(&conf.characters.display(character)), appearances, morae
+            );
+        }
+    }
+}
+
+// Every character that appears or speaks in any period, the most appearing first.
+fn timeline_characters(timeline: &Timeline, conf: &Config) -> Vec<String>{
+    let mut totals = HashMap::<String, usize>::new();
+    for (_, stats) in &timeline.periods{
+        let characters = stats.characters.keys().chain(stats.speaks.keys())
+            .filter(|character| !conf.is_hidden(character))
+            .collect::<HashSet<_>>();
+        for character in characters{
+            let appearances = stats.characters.get(character).copied().unwrap_or(0);
+            update(&mut totals, character, |x| x + appearances);
+        }
+    }
+    let mut characters = totals.into_iter().collect::<Vec<_>>();
+    characters.sort_by(|(a, x), (b, y)| y.cmp(x).then(a.cmp(b)));
+    characters.into_iter().map(|(character, _)| character).collect()
+}
+
+fn timeline_cell(stats: &Stats, character: &str) -> [usize; 2]{
+    [
+        stats.characters.get(character).copied().unwrap_or(0),
+        stats.speaks.get(character).copied().unwrap_or(0),
+    ]
+}
+
+/// Resolves aliases to the character's id. Names not in a non-empty registry are warned about once.
+fn resolve_character(
+    reg: &Registry, name: String, unknown: &mut HashSet<String>, log: &mut String
+) -> String{
+    if reg.is_empty() || reg.get(&name).is_some(){
+        return reg.canonical(&name);
+    }
+    if unknown.insert(name.clone()){
+        let _ = writeln!(log, "Warning: character \"{}\" is not in the character registry.", name);
+    }
+    name
+}
+
+/// A location such as `"school/classroom"` is a part of every location before it in the path.
+/// The outermost location can have parents declared in the `locations` table of the series.
+/// Returns the location itself and all of it's ancestors, outermost first.
+pub fn location_ancestry(location: &str, parents: &HashMap<String, String>) -> Vec<String>{
+    let mut parts = location.split('/').map(|part| part.trim()).filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    // pictures without a location are still counted, under the empty name
+    if parts.is_empty() { return vec![String::new()]; }
+    while let Some(parent) = parents.get(parts[0]){
+        if parts.contains(&parent.as_str()) { break; }
+        parts.insert(0, parent);
+    }
+    let mut path = String::new();
+    let mut res = Vec::new();
+    for part in parts{
+        if !path.is_empty(){
+            path.push('/');
+        }
+        path.push_str(part);
+        res.push(path.clone());
+    }
+    res
+}
+
+fn location_parent(location: &str) -> Option<&str>{
+    location.rsplit_once('/').map(|(parent, _)| parent)
+}
+
+fn location_rows(
+    locs: &HashMap<String, (usize, usize)>, parent: Option<&str>, rows: &mut Vec<LocationRow>
+){
+    let mut children = locs.iter()
+        .filter(|(name, _)| location_parent(name) == parent)
+        .collect::<Vec<_>>();
+    children.sort_unstable_by(|(a, x), (b, y)| y.cmp(x).then(a.cmp(b)));
+    for (name, (appearances, morae)) in children{
+        rows.push(LocationRow{
+            location: name.clone(),
+            appearances: *appearances,
+            morae: *morae,
+        });
+        location_rows(locs, Some(name), rows);
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn accumulate(chapter: &str, conf: &Config, stats: &mut Stats) -> String{
+        let chapter = toml::from_str::<Chapter>(chapter).unwrap();
+        let mut log = String::new();
+        accumulate_stats(chapter, conf, stats, &mut log);
+        log
+    }
+
+    #[test]
+    fn test_text_kinds(){
+        let mut stats = Stats::default();
+        let log = accumulate(r#"
+            title = "t"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            characters = ["a", "b"]
+            [[pic.text]]
+            from = "a"
+            to = "b"
+            lines = "あいう"
+            [[pic.text]]
+            kind = "thought"
+            from = "b"
+            lines = "かき"
+            [[pic.text]]
+            kind = "sign"
+            lines = "さし"
+            [[pic.text]]
+            kind = "sfx"
+            lines = "ドン"
+        "#, &Config::default(), &mut stats);
+        assert_eq!(stats.rp.morae, 7);
+        assert_eq!(stats.speaks, HashMap::from([("a".to_string(), 3)]));
+        assert_eq!(stats.spoken_to, HashMap::from([("b".to_string(), 3)]));
+        assert_eq!(stats.thinks, HashMap::from([("b".to_string(), 2)]));
+        assert!(!log.contains("has no \"from\""));
+    }
+
+    #[test]
+    fn test_missing_speaker(){
+        let mut stats = Stats::default();
+        let log = accumulate(r#"
+            title = "t"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            [[pic.text]]
+            lines = "あ"
+            [[pic.text]]
+            kind = "narration"
+            lines = "い"
+        "#, &Config::default(), &mut stats);
+        assert!(log.contains("Warning: speech text has no \"from\""));
+        assert!(log.contains("Warning: narration text has no \"from\""));
+    }
+
+    #[test]
+    fn test_timeline_characters(){
+        let chapter = |nr: usize, characters: &str| format!(r#"
+            title = "t"
+            volume = 1
+            chapter = {}
+            [[pic]]
+            characters = {}
+            [[pic.text]]
+            from = "a"
+            lines = "あ"
+        "#, nr, characters);
+        let conf = Config::default();
+        let mut timeline = Timeline::new(Breakdown::Chapter);
+        for (nr, characters) in [(1, r#"["a", "b"]"#), (2, r#"["b"]"#)]{
+            let chapter = toml::from_str::<Chapter>(&chapter(nr, characters)).unwrap();
+            accumulate_timeline(&chapter, &conf, &mut timeline);
+        }
+        assert_eq!(timeline_characters(&timeline, &conf), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn test_non_diegetic(){
+        let chapter = r#"
+            title = "t"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            characters = ["a", "b", "narator"]
+            [[pic.text]]
+            from = "a"
+            to = "b"
+            lines = "あいう"
+            [[pic.text]]
+            from = "narator"
+            to = "a"
+            lines = "かきくけ"
+        "#;
+        let mut conf = toml::from_str::<Config>(r#"non_diegetic = ["narator"]"#).unwrap();
+        let mut stats = Stats::default();
+        accumulate(chapter, &conf, &mut stats);
+        let report = stats_report(stats.clone(), &conf);
+        let ranking = |key: &str| report.rankings.iter().find(|r| r.key == key).unwrap();
+        let names = |key: &str| ranking(key).entries.iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names("character_appearances"), vec!["a", "b"]);
+        assert_eq!(names("morae_spoken"), vec!["a"]);
+        assert_eq!(ranking("morae_spoken").total, Value::Count(7));
+        assert_eq!(names("conversation_pairs"), vec!["a, b"]);
+        assert_eq!(names("character_prominence"), vec!["a", "b"]);
+
+        let nodes = |conf: &Config| interaction_graph(&stats, conf, NodeWeight::Appearances).nodes
+            .into_iter().map(|node| node.id).collect::<Vec<_>>();
+        assert_eq!(nodes(&conf), vec!["a", "b", "narator"]);
+        conf.hide_non_diegetic = true;
+        assert_eq!(nodes(&conf), vec!["a", "b"]);
+        assert_eq!(interaction_graph(&stats, &conf, NodeWeight::Appearances).edges.len(), 1);
+    }
+
+    fn counts(counts: &[(&str, usize)]) -> HashMap<String, usize>{
+        counts.iter().map(|(name, count)| (name.to_string(), *count)).collect()
+    }
+
+    fn scores(composition: &Composition) -> Vec<(&str, f64)>{
+        composition.characters.iter().map(|c| (c.name.as_str(), c.score)).collect()
+    }
+
+    #[test]
+    fn test_default_prominence(){
+        let appearances = counts(&[("a", 3), ("b", 1)]);
+        let speaks = counts(&[("a", 6), ("b", 2)]);
+        let spoken_to = counts(&[("a", 2), ("b", 6)]);
+        let composition = prominence(
+            &Prominence::default(), &appearances, &speaks, &spoken_to, &HashMap::new(),
+            &HashSet::new()
+        );
+        // the old score: 50 * (appearance share + share of morae spoken and spoken to)
+        let old = |name: &str| 50.0 * (appearances[name] as f64 / 4.0
+            + (speaks[name] + spoken_to[name]) as f64 / 16.0);
+        assert_eq!(scores(&composition), vec![("a", old("a")), ("b", old("b"))]);
+        assert_eq!(scores(&composition), vec![("a", 62.5), ("b", 37.5)]);
+        assert_eq!(composition.parts.len(), 3);
+    }
+
+    #[test]
+    fn test_partners_prominence(){
+        let weights = Prominence{
+            appearances: 1.0, morae_spoken: 0.0, morae_spoken_to: 0.0, partners: 2.0
+        };
+        let composition = prominence(
+            &weights, &counts(&[("a", 3), ("b", 1)]), &counts(&[("a", 4)]), &HashMap::new(),
+            &counts(&[("a", 1), ("b", 1)]), &HashSet::new()
+        );
+        let parts = composition.parts.iter().map(|part| part.name).collect::<Vec<_>>();
+        assert_eq!(parts, vec!["appearances", "partners"]);
+        let rounded = composition.characters.iter()
+            .map(|c| (c.name.as_str(), c.parts.iter().map(|p| (p * 100.0).round()).collect()))
+            .collect::<Vec<(_, Vec<_>)>>();
+        // 3/4 + 2 * 1/2 for a and 1/4 + 2 * 1/2 for b, scaled to add up to 100
+        assert_eq!(rounded, vec![("a", vec![2500.0, 3333.0]), ("b", vec![833.0, 3333.0])]);
+    }
+
+    #[test]
+    fn test_scenes(){
+        let mut stats = Stats::default();
+        let chapter = |nr: usize| format!(r#"
+            title = "t"
+            volume = 1
+            chapter = {}
+            [[pic]]
+            location = "home"
+            [[pic.text]]
+            from = "a"
+            lines = "あいう"
+            [[pic]]
+            [[pic.text]]
+            from = "a"
+            lines = "かき"
+            [[pic]]
+            location = "school"
+            [[pic]]
+            location = "home"
+        "#, nr);
+        accumulate(&chapter(1), &Config::default(), &mut stats);
+        accumulate(&chapter(2), &Config::default(), &mut stats);
+        let scene = |scenes: usize, pictures: usize, morae: usize| (scenes, pictures, morae);
+        assert_eq!(stats.scenes, HashMap::from([
+            ("home".to_string(), scene(4, 6, 10)),
+            ("school".to_string(), scene(2, 2, 0)),
+        ]));
+        // the scene at home ending chapter 1 is not followed by the one starting chapter 2
+        let transition = |from: &str, to: &str| (from.to_string(), to.to_string());
+        assert_eq!(stats.transitions, HashMap::from([
+            (transition("home", "school"), 2),
+            (transition("school", "home"), 2),
+        ]));
+        let report = stats_report(stats, &Config::default());
+        let scenes = report.scenes.unwrap().into_iter()
+            .map(|row| (row.location, row.scenes, row.average_pictures, row.average_morae))
+            .collect::<Vec<_>>();
+        assert_eq!(scenes, vec![
+            ("home".to_string(), 4, 1.5, 2.5),
+            ("school".to_string(), 2, 1.0, 0.0),
+        ]);
+
+        // a location written with or without it's parents is one place
+        let mut conf = Config::default();
+        conf.locations.insert("classroom".to_string(), "school".to_string());
+        let mut stats = Stats::default();
+        accumulate(r#"
+            title = "t"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            location = "classroom"
+            [[pic]]
+            location = "school/classroom"
+            [[pic]]
+            location = "classroom"
+        "#, &conf, &mut stats);
+        assert_eq!(stats.scenes, HashMap::from([
+            ("school/classroom".to_string(), scene(1, 3, 0)),
+        ]));
+        assert!(stats.transitions.is_empty());
+    }
+
+    #[test]
+    fn test_location_ancestry(){
+        let parents = HashMap::from([
+            ("classroom".to_string(), "school".to_string()),
+            ("school".to_string(), "town".to_string()),
+            ("a".to_string(), "b".to_string()),
+            ("b".to_string(), "a".to_string()),
+        ]);
+        let ancestry = |location: &str| location_ancestry(location, &parents);
+        assert_eq!(ancestry("home / kitchen/"), vec!["home", "home/kitchen"]);
+        assert_eq!(ancestry("classroom"), vec!["town", "town/school", "town/school/classroom"]);
+        assert_eq!(ancestry("school/classroom"), ancestry("classroom"));
+        // parents that are each others parent stop once the path would repeat
+        assert_eq!(ancestry("a"), vec!["b", "b/a"]);
+        assert_eq!(ancestry(""), vec![""]);
+
+        let mut locations = HashMap::new();
+        let pictures = [("school/", 1), ("classroom", 2), ("school/roof", 3), ("home", 4)];
+        for (location, morae) in pictures{
+            for loc in ancestry(location){
+                update(&mut locations, &loc, |(a, b)| (a + 1, b + morae));
+            }
+        }
+        let mut rows = Vec::new();
+        location_rows(&locations, None, &mut rows);
+        let rows = rows.into_iter()
+            .map(|row| (row.location, row.appearances, row.morae))
+            .collect::<Vec<_>>();
+        let row = |location: &str, appearances: usize, morae: usize|
+            (location.to_string(), appearances, morae);
+        assert_eq!(rows, vec![
+            row("town", 3, 6),
+            row("town/school", 3, 6),
+            row("town/school/roof", 1, 3),
+            row("town/school/classroom", 1, 2),
+            row("home", 1, 4),
+        ]);
+    }
+}
@@ -1,6 +1,6 @@
 use serde::Deserialize;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum Either<T, U>{ This(T), That(U) }
 
@@ -28,9 +28,11 @@ impl<T, U> Vectorize<T> for Option<U> where U: Vectorize<T>{
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct Chapter{
+    #[serde(default)]
     pub manga: String,
+    #[serde(default)]
     pub author: String,
     pub volume: usize,
     pub chapter: usize,
@@ -39,7 +41,7 @@ pub struct Chapter{
     pub pic: Vec<Pic>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct Pic{
     pub nr: Option<usize>,
     pub page: Option<usize>,
@@ -48,9 +50,11 @@ pub struct Pic{
     pub text: Option<Vec<Text>>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct Text{
-    pub from: OneOrMore<String>,
+    #[serde(default)]
+    pub kind: TextKind,
+    pub from: Option<OneOrMore<String>>,
     pub to: Option<OneOrMore<String>>,
     pub todo: Option<bool>,
     pub lines: OneOrMore<String>,
@@ -59,3 +63,23 @@ pub struct Text{
     pub notes: Option<OneOrMore<String>>,
 }
 
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TextKind{ #[default] Speech, Thought, Narration, Sign, Sfx }
+
+impl TextKind{
+    pub fn name(&self) -> &'static str{
+        match self{
+            Self::Speech => "speech",
+            Self::Thought => "thought",
+            Self::Narration => "narration",
+            Self::Sign => "sign",
+            Self::Sfx => "sfx",
+        }
+    }
+
+    /// Whether the text is said by someone and should name them in `from`.
+    pub fn has_speaker(&self) -> bool{
+        matches!(self, Self::Speech | Self::Thought | Self::Narration)
+    }
+}
@@ -1,119 +1,235 @@
 use crate::structure::*;
 use crate::japanese::*;
 use crate::report::*;
+use crate::config::*;
 
 use std::fmt::Write;
 
-pub fn write_transcription(chapter: Chapter, md: &mut String, log: &mut String){
-    let _ = writeln!(md, "{}{}", header(1), &chapter.title);
-    let _ = writeln!(md, "Manga: {}", chapter.manga);
-    let _ = writeln!(md, "Author: {}", chapter.author);
-    let _ = writeln!(md, "Volume: {}", chapter.volume);
-    let _ = writeln!(md, "Chapter: {}", chapter.chapter);
-    if let Some(subchap) = chapter.subchapter{
-        let _ = writeln!(md, "Sub Chapter: {}", subchap);
+/// A picture with texts, as it is transcribed in any format.
+pub struct PictureTranscript{
+    /// The page, when this is the first picture written on it.
+    pub page: Option<usize>,
+    pub nr: usize,
+    /// Texts are labeled when there is more than one, or when some of them are not speech.
+    pub labeled: bool,
+    pub texts: Vec<TextTranscript>,
+}
+
+/// A text with the lines of every section that is written, in the order of the config.
+/// The lines of a section still need the replacements of that section.
+pub struct TextTranscript{
+    pub kind: TextKind,
+    pub lines: Vec<String>,
+    pub kmap: Vec<[String; 2]>,
+    pub sections: Vec<(Section, Vec<String>)>,
+}
+
+impl TextTranscript{
+    /// Like "text 2" or "text 3 (thought)", for labeled texts.
+    pub fn label(&self, n: usize) -> String{
+        if self.kind == TextKind::Speech{
+            format!("text {}", n)
+        } else {
+            format!("text {} ({})", n, self.kind.name())
+        }
     }
+}
 
+/// Walks through the pictures with texts of a chapter, the part of the transcription that is
+/// the same in every format.
+pub fn transcript(chapter: &Chapter, conf: &Config, log: &mut String) -> Vec<PictureTranscript>{
+    let options = &conf.romanization;
+    let conf = &conf.transcription;
+    let mut pictures = Vec::new();
     let mut page = 0;
     let mut pic_nr = 1;
     let mut last_written_page = 0;
 
-    if chapter.pic.is_empty() { return; }
-    chapter_header_log(&chapter, log);
-
-    for picture in chapter.pic{
-        fn write_text(md: &mut String, log: &mut String, ident: usize, text: Text){
-            fn write_lines(md: &mut String, lines: &[String], reps: &[(&str, &str)]) {
-                for line in lines{
-                    let mut newline = line.clone();
-                    for (replacee, replacant) in reps{
-                        newline = newline.replace(replacee, replacant);
-                    }
-                    let _ = write!(md, "{} <br/> ", newline);
-                }
-                for _ in 0..7 { md.pop(); }
-            }
-            log_todo(&text, log);
-            let lines = text.lines.vectorize();
-            // transcription
-            let _ = write!(md, "{}", bullet(ident + 1));
-            write_lines(md, &lines, &[
-                (" ", ""), ("-", "ー"), ("~", "〜"), ("!", "！"), ("?", "？")
-            ]);
-            let _ = writeln!(md);
-            // kanji replacement
-            let replacements = if let Some(kmap) = text.kmap{
-                let rs = map_kanjis(&lines, kmap.vectorize().as_slice());
-                let _ = write!(md, "{}", bullet(ident + 1));
-                write_lines(md, &rs, &[(" ", "")]);
-                let _ = writeln!(md);
-                rs
+    if chapter.pic.is_empty() { return pictures; }
+    chapter_header_log(chapter, log);
+
+    for picture in &chapter.pic{
+        pic_nr = picture.nr.unwrap_or(pic_nr + 1);
+        page = picture.page.unwrap_or(page);
+
+        let texts = if let Some(texts) = &picture.text{ texts } else { continue; };
+
+        let new_page = page > last_written_page;
+        if new_page{
+            last_written_page = page;
+        }
+
+        let texts = texts.iter().map(|text| {
+            log_todo(text, log);
+            let lines = text.lines.clone().vectorize();
+            let kmap = text.kmap.clone().map(|kmap| kmap.vectorize());
+            let replacements = if let Some(kmap) = &kmap{
+                map_kanjis(&lines, kmap.as_slice())
             } else {
                 lines.clone()
             };
-            // romanize
-            if could_contain_kanji(&replacements){
-                let _ = writeln!(
-                    log,
-                    "Warning: lines {:#?} contain kanji or untranslateable characters.",
-                    replacements
-                );
-            } else {
-                let mut romanizeds = Vec::new();
-                for rep in &replacements{
-                    romanizeds.push(romanize(rep));
+            let transl = text.transl.clone().vectorize();
+            let notes = text.notes.clone().vectorize();
+            let mut sections = Vec::new();
+            for section in &conf.sections{
+                match section{
+                    Section::Original => sections.push((*section, strip_escapes(&lines))),
+                    Section::Kanji => if kmap.is_some(){
+                        sections.push((*section, strip_escapes(&replacements)));
+                    },
+                    Section::Romanized => if could_contain_kanji(&replacements){
+                        let _ = writeln!(
+                            log,
+                            "Warning: lines {:#?} contain kanji or untranslateable characters.",
+                            replacements
+                        );
+                    } else {
+                        let romanizeds = replacements.iter()
+                            .map(|rep| romanize_with(rep, options))
+                            .collect::<Vec<_>>();
+                        sections.push((*section, romanizeds));
+                    },
+                    Section::Translation => if !transl.is_empty(){
+                        sections.push((*section, transl.clone()));
+                    },
+                    Section::Notes => if !notes.is_empty(){
+                        sections.push((*section, notes.clone()));
+                    },
                 }
-                let _ = write!(md, "{}", bullet(ident + 1));
-                write_lines(md, &romanizeds, &[
-                    ("　", " "), ("ー", "-"), ("〜", "~"), ("！", "!"), ("？", "?")
-                ]);
-                let _ = writeln!(md);
             }
-            // translation
-            if let Some(transl) = text.transl{
-                let _ = write!(md, "{}", bullet(ident + 1));
-                write_lines(md, &transl.vectorize(), &[]);
-                let _ = writeln!(md);
-            }
-            // notes
-            if let Some(notes) = text.notes{
-                let _ = write!(md, "{}", bullet(ident + 1));
-                write_lines(md, &notes.vectorize(), &[]);
-                let _ = writeln!(md);
-            }
-        }
+            TextTranscript{ kind: text.kind, lines, kmap: kmap.unwrap_or_default(), sections }
+        }).collect::<Vec<_>>();
 
-        pic_nr = picture.nr.unwrap_or(pic_nr + 1);
-        page = picture.page.unwrap_or(page);
+        pictures.push(PictureTranscript{
+            page: if new_page { Some(page) } else { None },
+            nr: pic_nr,
+            labeled: texts.len() > 1 || texts.iter().any(|text| text.kind != TextKind::Speech),
+            texts,
+        });
+    }
+    pictures
+}
 
-        let text = if let Some(text) = picture.text{ text } else { continue; };
+/// Writes the transcription as Markdown. The romanized title of the manga comes from the series.
+pub fn write_transcription(
+    chapter: Chapter, romanized: Option<&str>, conf: &Config, md: &mut String, log: &mut String
+){
+    let pictures = transcript(&chapter, conf, log);
+    let conf = &conf.transcription;
+    let _ = writeln!(md, "{}{}", header(conf.title_header), &chapter.title);
+    if let Some(romanized) = romanized{
+        let _ = writeln!(md, "Manga: {} ({})", chapter.manga, romanized);
+    } else {
+        let _ = writeln!(md, "Manga: {}", chapter.manga);
+    }
+    let _ = writeln!(md, "Author: {}", chapter.author);
+    let _ = writeln!(md, "Volume: {}", chapter.volume);
+    let _ = writeln!(md, "Chapter: {}", chapter.chapter);
+    if let Some(subchap) = chapter.subchapter{
+        let _ = writeln!(md, "Sub Chapter: {}", subchap);
+    }
 
-        if page > last_written_page{
-            last_written_page = page;
-            let _ = writeln!(md, "{}Page: {}", header(5), page);
+    for picture in pictures{
+        if let Some(page) = picture.page{
+            let _ = writeln!(md, "{}Page: {}", header(conf.page_header), page);
         }
-        let _ = writeln!(md, "{}picture {}", bullet(0), pic_nr);
+        let _ = writeln!(md, "{}picture {}", bullet(conf, 0), picture.nr);
 
-        let multiple = text.len() > 1;
-        let ident = usize::from(multiple);
-        for (n, text) in text.into_iter().enumerate(){
-            if multiple{
-                let _ = writeln!(md, "{}text {}", bullet(1), n + 1);
+        let ident = usize::from(picture.labeled);
+        for (n, text) in picture.texts.iter().enumerate(){
+            if picture.labeled{
+                let _ = writeln!(md, "{}{}", bullet(conf, 1), text.label(n + 1));
+            }
+            for (section, lines) in &text.sections{
+                let lines = lines.iter()
+                    .map(|line| replace(line, conf.replacements(*section)))
+                    .collect::<Vec<_>>();
+                let _ = writeln!(
+                    md, "{}{}", bullet(conf, ident + 1), lines.join(&conf.line_separator)
+                );
             }
-            write_text(md, log, ident, text);
         }
     }
 }
 
+/// Does every replacement on the string, in order.
+pub fn replace(string: &str, reps: &[[String; 2]]) -> String{
+    let mut new = string.to_string();
+    for [replacee, replacant] in reps{
+        new = new.replace(replacee, replacant);
+    }
+    new
+}
+
 fn header(rank: usize) -> String{
     let mut temp = "#".repeat(rank);
     temp.push(' ');
     temp
 }
 
-fn bullet(ident: usize) -> String{
-    let mut temp = "  ".repeat(ident);
-    temp.push_str("- ");
+fn bullet(conf: &TranscriptionConfig, ident: usize) -> String{
+    let mut temp = conf.indent.repeat(ident);
+    temp.push_str(&conf.bullet);
     temp
 }
 
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn test_transcription_config(){
+        let conf = toml::from_str::<Config>(r#"
+            [transcription]
+            sections = ["translation", "original", "kanji"]
+            line_separator = " / "
+            original_replacements = [["!", "！"]]
+            kanji_replacements = [[" ", ""]]
+            title_header = 2
+            page_header = 3
+            bullet = "* "
+            indent = "    "
+        "#).unwrap();
+        let chapter = toml::from_str::<Chapter>(r#"
+            manga = "m"
+            author = "a"
+            title = "t"
+            volume = 1
+            chapter = 2
+            [[pic]]
+            nr = 1
+            page = 1
+            [[pic.text]]
+            from = "a"
+            lines = ["朝 だ!", "おきて"]
+            kmap = ["朝", "あさ"]
+            transl = "Morning!"
+            [[pic]]
+            [[pic.text]]
+            from = "a"
+            lines = "はい"
+            [[pic.text]]
+            kind = "sign"
+            lines = "がっこう"
+        "#).unwrap();
+        let mut md = String::new();
+        write_transcription(chapter, None, &conf, &mut md, &mut String::new());
+        assert_eq!(md, "\
+## t
+Manga: m
+Author: a
+Volume: 1
+Chapter: 2
+### Page: 1
+* picture 1
+    * Morning!
+    * 朝 だ！ / おきて
+    * あさだ! / おきて
+* picture 2
+    * text 1
+        * はい
+    * text 2 (sign)
+        * がっこう
+");
+    }
+}
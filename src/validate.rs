@@ -0,0 +1,163 @@
+use crate::structure::*;
+use crate::report::*;
+use crate::config::*;
+
+use std::collections::HashSet;
+
+/// Checks the numbering of pictures and pages, that every picture has content and lists the
+/// characters speaking in it, and that every speech, thought and narration has a speaker.
+/// Characters that are not in the story, like a narrator, need not be in the picture.
+pub fn validate_chapter(chapter: &Chapter, conf: &Config, issues: &mut Vec<Issue>){
+    let reg = &conf.characters;
+    let mut location = Location::new(chapter);
+    let mut seen = Vec::new();
+    let mut last_nr = None;
+
+    for (i, picture) in chapter.pic.iter().enumerate(){
+        let last_page = location.page;
+        location.advance(picture);
+        let mut issue = |text: Option<usize>, message: String| issues.push(Issue{
+            location,
+            text,
+            message,
+        });
+
+        match picture.page{
+            None if i == 0 => issue(None, "first picture has no page number.".to_string()),
+            Some(page) if page < last_page => issue(
+                None, format!("page goes back from {} to {}.", last_page, page)
+            ),
+            _ => {},
+        }
+
+        if let Some(nr) = picture.nr{
+            if seen.contains(&nr){
+                issue(None, format!("picture number {} is used more than once.", nr));
+            } else if let Some(last) = last_nr{
+                if nr < last{
+                    issue(None, format!("picture number goes back from {} to {}.", last, nr));
+                } else if nr == last + 2{
+                    issue(None, format!("picture number {} is skipped.", last + 1));
+                } else if nr > last + 2{
+                    issue(None, format!("picture numbers {} to {} are skipped.", last + 1, nr - 1));
+                }
+            }
+            seen.push(nr);
+            last_nr = Some(last_nr.map_or(nr, |last: usize| last.max(nr)));
+        } else {
+            issue(None, "picture has no number.".to_string());
+            // it is numbered as the one after the last
+            last_nr = last_nr.map(|last| last + 1);
+        }
+
+        let characters = picture.characters.clone().vectorize().into_iter()
+            .map(|name| reg.canonical(&name))
+            .collect::<Vec<_>>();
+        let texts = picture.text.clone().unwrap_or_default();
+        if characters.is_empty() && texts.is_empty(){
+            issue(None, "picture has neither characters nor text.".to_string());
+        }
+        for (n, text) in texts.into_iter().enumerate(){
+            if text.kind.has_speaker() && text.from.clone().vectorize().is_empty(){
+                issue(Some(n + 1), format!("{} text has no `from`.", text.kind.name()));
+            }
+            let mut missing = text.from.vectorize();
+            missing.extend(text.to.vectorize());
+            let mut listed = HashSet::new();
+            missing.retain(|name| {
+                if conf.is_non_diegetic(name) { return false; }
+                let id = reg.canonical(name);
+                !characters.contains(&id) && listed.insert(id)
+            });
+            if !missing.is_empty(){
+                issue(Some(n + 1), format!(
+                    "{} not in the characters of the picture.", missing.join(", ")
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn validate(chapter: &str, conf: &Config) -> Vec<String>{
+        let chapter = toml::from_str::<Chapter>(chapter).unwrap();
+        let mut issues = Vec::new();
+        validate_chapter(&chapter, conf, &mut issues);
+        issues.into_iter().map(|issue| issue.message).collect()
+    }
+
+    #[test]
+    fn test_speakers(){
+        let issues = validate(r#"
+            title = "t"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            nr = 1
+            page = 1
+            characters = "a"
+            [[pic.text]]
+            kind = "thought"
+            lines = "あ"
+            [[pic.text]]
+            kind = "sign"
+            lines = "い"
+            [[pic.text]]
+            from = "a"
+            to = ["b", "c", "b"]
+            lines = "う"
+        "#, &Config::default());
+        assert_eq!(issues, vec![
+            "thought text has no `from`.",
+            "b, c not in the characters of the picture.",
+        ]);
+    }
+
+    #[test]
+    fn test_numbering(){
+        let issues = validate(r#"
+            title = "t"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            nr = 1
+            page = 1
+            characters = "a"
+            [[pic]]
+            characters = "a"
+            [[pic]]
+            nr = 3
+            characters = "a"
+            [[pic]]
+            nr = 5
+            characters = "a"
+        "#, &Config::default());
+        assert_eq!(issues, vec!["picture has no number.", "picture number 4 is skipped."]);
+    }
+
+    #[test]
+    fn test_non_diegetic(){
+        let conf = Config{
+            non_diegetic: vec!["narrator".to_string(), "audience".to_string()],
+            ..Default::default()
+        };
+        let issues = validate(r#"
+            title = "t"
+            volume = 1
+            chapter = 1
+            [[pic]]
+            nr = 1
+            page = 1
+            characters = "a"
+            [[pic.text]]
+            kind = "narration"
+            from = "narrator"
+            to = ["audience", "b"]
+            lines = "あ"
+        "#, &conf);
+        assert_eq!(issues, vec!["b not in the characters of the picture."]);
+    }
+}